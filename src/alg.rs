@@ -1,11 +1,15 @@
-#![cfg(test)]
+// explores the libraries directly, so needs the optional ones
+#![cfg(all(test, feature = "faer", feature = "peroxide"))]
 use approx::assert_relative_eq;
 // use ndarray::{array, Array1, Array2};
 // use ndarray_linalg::Solve;
 use faer::{assert_matrix_eq, linalg::matmul::matmul, mat, Mat, Parallelism};
-use nalgebra::{Matrix3, Matrix3x1, Vector3};
+use nalgebra::{DMatrix, DVector, Matrix3, Matrix3x1, Vector3};
 use faer::linalg::triangular_solve::solve_lower_triangular_in_place;
 use peroxide::fuga::{matrix, LinearAlgebra, Shape::Col, SolveKind};
+use crate::field::rational;
+use crate::plu::{back_substitution, forward_substitution, plu};
+use crate::row_reduction::rref;
 
 #[test]
 fn multiply_vector_matrix() {
@@ -121,72 +125,51 @@ fn solve_single_solution_equations_system() {
 
 #[test]
 fn echelon_form() {
-        let a = matrix(
-            vec![
-                1.0, 2.0, -1.0, //
-                1.0, -1.0, -2.0, //
-                1.0, 2.0, 3.0, //
-                2.0, 7.0, 7.0,
-            ],
-            3,
-            4,
-            Col,
-        );
+        #[rustfmt::skip]
+        let a = DMatrix::from_row_slice(3, 4, &[
+            1, 1, 1, 2,
+            2, -1, 2, 7,
+            -1, -2, 3, 7,
+        ]).map(|v| rational(v, 1));
 
         // Calculate reduced row echelon form
-        let rref_a = a.rref();
-        // println!("rref_a: {}", rref_a);
+        let rref_a = rref(&a);
+        // println!("rref_a: {}", rref_a.matrix);
 
         // the solution: x1 = 1, x2 = -1, x3 = 2
-        let expected= matrix(
-            vec![
-                1.0, 0.0, 0.0, //
-                0.0, 1.0, 0.0, //
-                0.0, 0.0, 1.0, //
-                1.0, -1.0, 2.0,
-            ],
-            3,
-            4,
-            Col,
-        );
-
-        assert_eq!(rref_a, expected);
+        #[rustfmt::skip]
+        let expected = DMatrix::from_row_slice(3, 4, &[
+            1, 0, 0, 1,
+            0, 1, 0, -1,
+            0, 0, 1, 2,
+        ]).map(|v| rational(v, 1));
+
+        assert_eq!(rref_a.matrix, expected);
+        assert_eq!(rref_a.pivot_columns, vec![0, 1, 2]);
 }
 
 #[test]
 fn echelon_form_with_free_variables() {
-        let a = matrix(
-            vec![
-                3.0, 8.0, 0.0, //
-                0.0, 0.0, 2.0, //
-                1.0, 0.0, 2.0, //
-                0.0, 2.0, 1.0, //
-                0.0, 0.0, 0.0
-            ],
-            3,
-            5,
-            Col,
-        );
+        #[rustfmt::skip]
+        let a = DMatrix::from_row_slice(3, 5, &[
+            3, 0, 1, 0, 0,
+            8, 0, 0, 2, 0,
+            0, 2, 2, 1, 0,
+        ]).map(|v| rational(v, 1));
 
         // Calculate reduced row echelon form
-        let rref_a = a.rref();
-        println!("rref_a: {}", rref_a);
-
-        // // the solution: x1 = 1, x2 = -1, x3 = 2
-        let expected= matrix(
-            vec![
-                1.0, 0.0, 0.0, //
-                0.0, 1.0, 0.0, //
-                0.0, 0.0, 1.0, //
-                0.25, 1.25, -0.75, //
-                0.0, 0.0, 0.0, //
-            ],
-            3,
-            5,
-            Col,
-        );
-
-        assert_eq!(rref_a, expected);
+        let rref_a = rref(&a);
+
+        // x4 is free: x1 = -1/4 x4, x2 = -5/4 x4, x3 = 3/4 x4
+        #[rustfmt::skip]
+        let expected = DMatrix::from_row_slice(3, 5, &[
+            rational(1, 1), rational(0, 1), rational(0, 1), rational(1, 4), rational(0, 1),
+            rational(0, 1), rational(1, 1), rational(0, 1), rational(5, 4), rational(0, 1),
+            rational(0, 1), rational(0, 1), rational(1, 1), rational(-3, 4), rational(0, 1),
+        ]);
+
+        assert_eq!(rref_a.matrix, expected);
+        assert_eq!(rref_a.free_columns, vec![3, 4]);
 }
 
 #[test]
@@ -259,19 +242,24 @@ fn lu_decomposition() {
         assert_relative_eq!(u[(2,2)], -1.428571, epsilon = e);
 
         // check whether solving LU "by hand" renders the same result as solving the original matrix
-        // incidentally using LU internally to solve these as well
-        let y = l.solve(b, SolveKind::LU);
-        let x = u.solve(&y, SolveKind::LU);
-
-        // this gives [4.0, -0.2, -3.4], 
-        // which is different to solution [3.0, -1.0, 0.0]
-        // what's wrong? TODO
-        println!("x = {:?}", x);
+        // with peroxide's l and u this gave [4.0, -0.2, -3.4] instead of [3.0, -1.0, 0.0]:
+        // peroxide's lu pivots rows and columns (lu.p, lu.q), so l * u is a permuted a, not a,
+        // and solving with l and u alone ignores the permutation.
+        // our PLU keeps P and solves Ly = Pb, Ux = y
+        #[rustfmt::skip]
+        let a = DMatrix::from_row_slice(3, 3, &[
+            2.0, 1.0, 3.0,
+            1.0, -1.0, 2.0,
+            3.0, 2.0, 1.0,
+        ]);
+        let plu = plu(&a);
+        let y = forward_substitution(&plu.l, &plu.permute(&DVector::from_row_slice(b)));
+        let x = back_substitution(&plu.u, &y);
 
         assert_relative_eq!(res[0], x[0], epsilon = e);
         assert_relative_eq!(res[1], x[1], epsilon = e);
         assert_relative_eq!(res[2], x[2], epsilon = e);
-
+        assert_relative_eq!(plu.determinant().unwrap(), 10.0, epsilon = e);
 }
 
 
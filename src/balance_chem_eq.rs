@@ -1,59 +1,495 @@
+use crate::field::{to_primitive_integers, Field};
+use crate::linear_system::null_space;
+use nalgebra::{DMatrix, DVector};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed};
+
+/// a molecule (or ion, crystal..) with the count of each element, in order of first appearance in the formula
+#[derive(Debug, Clone, PartialEq)]
+pub struct Species {
+    pub formula: String,
+    pub elements: Vec<(String, u32)>,
+    /// e.g. -1 for MnO4^-, 2 for Fe^{2+}. electrons (e^-) are a species without elements and charge -1
+    pub charge: i64,
+}
+
+/// reactants -> products, without coefficients (finding those is what balancing is about)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reaction {
+    pub reactants: Vec<Species>,
+    pub products: Vec<Species>,
+}
+
+impl Species {
+    pub fn count(&self, element: &str) -> u32 {
+        self.elements
+            .iter()
+            .find(|(symbol, _)| symbol == element)
+            .map(|(_, count)| *count)
+            .unwrap_or(0)
+    }
+
+    /// same elements and charge, regardless of how the formula was written (e.g. H^+ and H^{+})
+    fn is_same(&self, other: &Species) -> bool {
+        self.elements == other.elements && self.charge == other.charge
+    }
+}
+
+impl Reaction {
+    /// reactants, then products
+    pub fn species(&self) -> impl Iterator<Item = &Species> {
+        self.reactants.iter().chain(self.products.iter())
+    }
+
+    /// all elements in the reaction, in order of first appearance
+    pub fn elements(&self) -> Vec<&str> {
+        let mut elements: Vec<&str> = vec![];
+        for (symbol, _) in self.species().flat_map(|s| s.elements.iter()) {
+            if !elements.contains(&symbol.as_str()) {
+                elements.push(symbol);
+            }
+        }
+        elements
+    }
+
+    pub fn has_charges(&self) -> bool {
+        self.species().any(|s| s.charge != 0)
+    }
+
+    /// what the rows of the composition matrix stand for: the elements, and "charge" if there's a charge row
+    pub fn conserved_quantities(&self) -> Vec<String> {
+        let mut quantities: Vec<String> = self.elements().iter().map(|e| e.to_string()).collect();
+        if self.has_charges() {
+            quantities.push("charge".to_string());
+        }
+        quantities
+    }
+
+    /// e.g. "C3H8 + 5O2 -> 3CO2 + 4H2O" for the coefficients [1, 5, 3, 4]
+    pub fn format_balanced(&self, coefficients: &[BigInt]) -> String {
+        let terms: Vec<String> = self
+            .species()
+            .zip(coefficients)
+            .map(|(species, coefficient)| match coefficient.is_one() {
+                true => species.formula.clone(),
+                false => format!("{}{}", coefficient, species.formula),
+            })
+            .collect();
+        let (reactants, products) = terms.split_at(self.reactants.len());
+        format!("{} -> {}", reactants.join(" + "), products.join(" + "))
+    }
+
+    /// one row per element, one column per species (reactants, then products), with the atom counts
+    /// if any species is charged, a last row with the charges, since charge has to be conserved too
+    pub fn composition_matrix(&self) -> DMatrix<i64> {
+        let elements = self.elements();
+        let species: Vec<&Species> = self.species().collect();
+        let rows = elements.len() + usize::from(self.has_charges());
+        DMatrix::from_fn(rows, species.len(), |r, c| match elements.get(r) {
+            Some(element) => species[c].count(element) as i64,
+            None => species[c].charge,
+        })
+    }
+
+    /// the composition matrix with the product columns negated:
+    /// putting everything on the left side, the balanced coefficients x are the solutions of Ax = 0
+    pub fn conservation_matrix<T: Field>(&self) -> DMatrix<T> {
+        let reactants = self.reactants.len();
+        let composition = self.composition_matrix();
+        DMatrix::from_fn(composition.nrows(), composition.ncols(), |r, c| {
+            let count = composition[(r, c)];
+            T::from_i64(if c < reactants { count } else { -count })
+        })
+    }
+}
+
+/// result of balancing a reaction
+#[derive(Debug, Clone, PartialEq)]
+pub enum Balance {
+    /// the smallest positive integer coefficients, one per species (reactants, then products)
+    Unique(Vec<BigInt>),
+    /// several independent ways to balance, e.g. H2 + O2 -> H2O + H2O2.
+    /// every balancing is a combination of these (integer scaled) basis vectors, which can have negative or 0 entries
+    Multiple(Vec<Vec<BigInt>>),
+    /// only possible with all coefficients 0, or with some 0 or negative (i.e. species missing or on the wrong side)
+    Impossible,
+}
+
+/// where a redox reaction happens, which determines what we can add to balance H and O
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Medium {
+    /// H2O and H^+
+    Acidic,
+    /// H2O and OH^-
+    Basic,
+}
+
+impl Medium {
+    fn species(&self) -> [Species; 2] {
+        let ion = match self {
+            Medium::Acidic => "H^+",
+            Medium::Basic => "OH^-",
+        };
+        [ion, "H2O"].map(|formula| parse_formula(formula).expect("valid formula"))
+    }
+}
+
+impl Reaction {
+    /// finds the coefficients from the exact null space of the conservation matrix
+    pub fn balance(&self) -> Balance {
+        let basis = self.null_space();
+        match basis.len() {
+            0 => Balance::Impossible,
+            1 => {
+                let mut coefficients = to_primitive_integers(basis[0].as_slice());
+                if coefficients.iter().all(|c| c.is_negative()) {
+                    coefficients = coefficients.into_iter().map(|c| -c).collect();
+                }
+                if coefficients.iter().all(|c| c.is_positive()) {
+                    Balance::Unique(coefficients)
+                } else {
+                    Balance::Impossible
+                }
+            }
+            _ => Balance::Multiple(
+                basis
+                    .iter()
+                    .map(|v| to_primitive_integers(v.as_slice()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// balances a redox reaction (or half-reaction), adding H2O and H^+ / OH^- where needed
+    /// returns the reaction with the added species (on the side they're needed) together with its balance
+    pub fn balance_in(&self, medium: Medium) -> (Reaction, Balance) {
+        // we don't know yet on which side they go, so we add them as products and let the sign tell us
+        let mut extended = self.clone();
+        for species in medium.species() {
+            if !self.species().any(|s| s.is_same(&species)) {
+                extended.products.push(species);
+            }
+        }
+
+        let basis = extended.null_space();
+        if basis.len() != 1 {
+            let balance = extended.balance();
+            return (extended, balance);
+        }
+
+        let mut coefficients = to_primitive_integers(basis[0].as_slice());
+        let original = self.reactants.len() + self.products.len();
+        if coefficients[..original].iter().all(|c| c.is_negative()) {
+            coefficients = coefficients.into_iter().map(|c| -c).collect();
+        }
+        if !coefficients[..original].iter().all(|c| c.is_positive()) {
+            return (extended, Balance::Impossible);
+        }
+
+        // negative added products are reactants, and those with 0 aren't needed
+        let mut balanced = self.clone();
+        let mut reactant_coefficients = coefficients[..self.reactants.len()].to_vec();
+        let mut product_coefficients = coefficients[self.reactants.len()..original].to_vec();
+        for (species, coefficient) in extended.products[self.products.len()..]
+            .iter()
+            .zip(&coefficients[original..])
+        {
+            if coefficient.is_negative() {
+                balanced.reactants.push(species.clone());
+                reactant_coefficients.push(-coefficient);
+            } else if coefficient.is_positive() {
+                balanced.products.push(species.clone());
+                product_coefficients.push(coefficient.clone());
+            }
+        }
+        reactant_coefficients.extend(product_coefficients);
+        (balanced, Balance::Unique(reactant_coefficients))
+    }
+
+    fn null_space(&self) -> Vec<DVector<BigRational>> {
+        null_space(&self.conservation_matrix::<BigRational>())
+    }
+}
+
+const ARROWS: [&str; 4] = ["->", "→", "=>", "="];
+
+/// parses e.g. "C3H8 + O2 -> CO2 + H2O"
+pub fn parse_reaction(str: &str) -> Result<Reaction, String> {
+    let (reactants, products) = ARROWS
+        .iter()
+        .find_map(|arrow| str.split_once(arrow))
+        .ok_or_else(|| format!("missing arrow (one of {:?}) in: {}", ARROWS, str))?;
+
+    Ok(Reaction {
+        reactants: parse_side(reactants)?,
+        products: parse_side(products)?,
+    })
+}
+
+fn parse_side(str: &str) -> Result<Vec<Species>, String> {
+    split_species(str)
+        .into_iter()
+        .map(|s| parse_formula(s.trim()))
+        .collect()
+}
+
+/// splits on the '+' between species, but not on the ones in charges like H^+ or Fe^{3+}
+fn split_species(str: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut in_charge = false;
+    let mut in_braces = false;
+    for (i, c) in str.char_indices() {
+        match c {
+            '^' => in_charge = true,
+            '{' if in_charge => in_braces = true,
+            '}' => {
+                in_charge = false;
+                in_braces = false;
+            }
+            // without braces, the sign ends the charge
+            '+' | '-' if in_charge && !in_braces => in_charge = false,
+            '+' if !in_charge => {
+                parts.push(&str[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&str[start..]);
+    parts
+}
+
+/// parses a formula like "H2O", "Ca(OH)2", "K4[Fe(CN)6]" or the hydrate "CuSO4·5H2O" (also with '*' or '.')
+/// optionally with a charge after '^': "MnO4^-", "Fe^{2+}", "Fe^3+". "e^-" is an electron
+pub fn parse_formula(str: &str) -> Result<Species, String> {
+    let (body, charge) = match str.split_once('^') {
+        Some((body, charge)) => (
+            body,
+            parse_charge(charge).map_err(|e| format!("{} in: {}", e, str))?,
+        ),
+        None => (str, 0),
+    };
+    if body.is_empty() {
+        return Err("empty formula".to_string());
+    }
+    if body == "e" {
+        return Ok(Species {
+            formula: str.to_string(),
+            elements: vec![],
+            charge,
+        });
+    }
+
+    let mut elements = vec![];
+    for (i, part) in body.split(['·', '•', '*', '.']).enumerate() {
+        let mut parser = FormulaParser::new(part);
+        // the water count of hydrates, e.g. the 5 in CuSO4·5H2O
+        let multiplier = match i {
+            0 => 1,
+            _ => parser.number()?.unwrap_or(1),
+        };
+        for (symbol, count) in parser.group()? {
+            let count = parser.multiply(count, multiplier)?;
+            parser.add_element(&mut elements, &symbol, count)?;
+        }
+        if let Some(c) = parser.peek() {
+            return Err(format!("unexpected '{}' in: {}", c, str));
+        }
+    }
+
+    Ok(Species {
+        formula: str.to_string(),
+        elements,
+        charge,
+    })
+}
+
+/// "+", "-", "2+", "3-" (also "+2", "-3"), optionally in braces
+fn parse_charge(str: &str) -> Result<i64, String> {
+    let str = match str.strip_prefix('{') {
+        Some(rest) => rest
+            .strip_suffix('}')
+            .ok_or_else(|| "missing '}'".to_string())?,
+        None => str,
+    };
+    let sign_and_digits = |sign: char, digits: &str| -> Result<i64, String> {
+        let magnitude = match digits {
+            "" => 1,
+            _ => digits
+                .parse::<i64>()
+                .map_err(|_| format!("invalid charge '{}'", str))?,
+        };
+        Ok(if sign == '-' { -magnitude } else { magnitude })
+    };
+    match (str.chars().next(), str.chars().last()) {
+        (Some(sign @ ('+' | '-')), _) => sign_and_digits(sign, &str[1..]),
+        (_, Some(sign @ ('+' | '-'))) => sign_and_digits(sign, &str[..str.len() - 1]),
+        _ => Err(format!("invalid charge '{}'", str)),
+    }
+}
+
+/// recursive descent over the characters of a formula (without hydrate separators)
+struct FormulaParser<'a> {
+    str: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl<'a> FormulaParser<'a> {
+    fn new(str: &'a str) -> FormulaParser<'a> {
+        FormulaParser {
+            str,
+            chars: str.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// None if there are no digits
+    fn number(&mut self) -> Result<Option<u32>, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        if digits.is_empty() {
+            return Ok(None);
+        }
+        digits
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("count {} is too large in: {}", digits, self.str))
+    }
+
+    fn multiply(&self, count: u32, multiplier: u32) -> Result<u32, String> {
+        count
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("atom count is too large in: {}", self.str))
+    }
+
+    fn add_element(
+        &self,
+        elements: &mut Vec<(String, u32)>,
+        symbol: &str,
+        count: u32,
+    ) -> Result<(), String> {
+        match elements.iter_mut().find(|(s, _)| s == symbol) {
+            Some((_, existing)) => {
+                *existing = existing
+                    .checked_add(count)
+                    .ok_or_else(|| format!("atom count is too large in: {}", self.str))?
+            }
+            None => elements.push((symbol.to_string(), count)),
+        }
+        Ok(())
+    }
+
+    /// a sequence of elements and parenthesized groups, each with an optional count
+    /// stops at a closing bracket or the end
+    fn group(&mut self) -> Result<Vec<(String, u32)>, String> {
+        let mut elements = vec![];
+        while let Some(c) = self.peek() {
+            let parsed = match c {
+                ')' | ']' => break,
+                '(' | '[' => {
+                    let close = if c == '(' { ')' } else { ']' };
+                    self.pos += 1;
+                    let group = self.group()?;
+                    if self.peek() != Some(close) {
+                        return Err(format!("missing '{}' in: {}", close, self.str));
+                    }
+                    self.pos += 1;
+                    group
+                }
+                _ => vec![(self.element()?, 1)],
+            };
+            let count = self.number()?.unwrap_or(1);
+            for (symbol, n) in parsed {
+                let n = self.multiply(n, count)?;
+                self.add_element(&mut elements, &symbol, n)?;
+            }
+        }
+        Ok(elements)
+    }
+
+    /// an uppercase letter, optionally followed by lowercase ones, that's in the periodic table
+    fn element(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c.is_ascii_uppercase() => self.pos += 1,
+            Some(c) => return Err(format!("unexpected '{}' in: {}", c, self.str)),
+            None => return Err(format!("expected an element in: {}", self.str)),
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_lowercase()) {
+            self.pos += 1;
+        }
+        let symbol: String = self.chars[start..self.pos].iter().collect();
+        if ELEMENTS.contains(&symbol.as_str()) {
+            Ok(symbol)
+        } else {
+            Err(format!("unknown element '{}' in: {}", symbol, self.str))
+        }
+    }
+}
+
+#[rustfmt::skip]
+const ELEMENTS: [&str; 118] = [
+    "H", "He",
+    "Li", "Be", "B", "C", "N", "O", "F", "Ne",
+    "Na", "Mg", "Al", "Si", "P", "S", "Cl", "Ar",
+    "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As", "Se", "Br", "Kr",
+    "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In", "Sn", "Sb", "Te", "I", "Xe",
+    "Cs", "Ba",
+    "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb", "Dy", "Ho", "Er", "Tm", "Yb", "Lu",
+    "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl", "Pb", "Bi", "Po", "At", "Rn",
+    "Fr", "Ra",
+    "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk", "Cf", "Es", "Fm", "Md", "No", "Lr",
+    "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh", "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
 #[cfg(test)]
 mod test {
-    use bevy::prelude::default;
-    use nalgebra::Vector3;
-    use peroxide::fuga::{matrix, LinearAlgebra, Shape::Col};
+    use super::*;
+    use crate::field::rational;
+    use crate::linear_system::{LinearSystem, Solution};
+    use crate::row_reduction::rref;
 
     // based on an example from Lay's linear algebra
     #[test]
     fn balance_chem_eq() {
-        let mol1 = to_vector_molecule(Mol {
-            c: 3,
-            h: 8,
-            ..default()
-        });
-        let mol2 = to_vector_molecule(Mol { o: 2, ..default() });
-        let mol3 = to_vector_molecule(Mol {
-            c: 1,
-            o: 2,
-            ..default()
-        });
-        let mol4 = to_vector_molecule(Mol {
-            h: 2,
-            o: 1,
-            ..default()
-        });
-
-        let units = ReactionMoleculeUnits {
-            input: vec![mol1, mol2],
-            output: vec![mol3, mol4],
-        };
+        let reaction = parse_reaction("C3H8 + O2 -> CO2 + H2O").unwrap();
+        assert_eq!(reaction.elements(), vec!["C", "H", "O"]);
 
         // to represent the reaction as a matrix, we put all the molecules on the left side of the equation, leaving the zero vector on the right.
-        let all_cols = [
-            as_vec(units.input[0].clone()),
-            as_vec(units.input[1].clone()),
-            as_negated_vec(units.output[0].clone()),
-            as_negated_vec(units.output[1].clone()),
-            vec![0.0, 0.0, 0.0],
-        ]
-        .concat()
-        .to_owned();
-
-        let a = matrix(all_cols, 3, 4, Col);
+        // i.e. the columns are the atom counts of each molecule, negated for the products
+        let coefficients: DMatrix<BigRational> = reaction.conservation_matrix();
+        #[rustfmt::skip]
+        let expected = DMatrix::from_row_slice(3, 4, &[
+            3, 0, -1, 0,
+            8, 0, 0, -2,
+            0, 2, -2, -1,
+        ]).map(|v| rational(v, 1));
+        assert_eq!(coefficients, expected);
+
+        let a = coefficients.clone().insert_column(4, rational(0, 1));
 
         // calculate reduced row echelon form
         // needed for the 4x3 system, with x4 as free variable (multiple solutions)
         // not sure how or whether it's possible to do this with the other matrix solvers - seem optimized / to work only for square matrices?
-        let rref_a = a.rref();
-        println!("rref_a: {:?}", rref_a);
+        // exact rationals, so the coefficients come out as fractions (-1/4, -5/4, -3/4) and we can compare exactly
+        let rref_a = rref(&a);
+        assert_eq!(rref_a.free_columns, vec![3, 4]);
 
         // x4, the free variable, is the molecule count of the last molecule
         // the count of the other molecules depends on this
 
         // remember that coefficients are the number of atoms of each element in the respective molecule:
-        let c4 = rref_a.col(3);
-        println!("c4 (water) coefficients (atom count) {:?}", c4);
+        let c4 = rref_a.matrix.column(3);
 
         // so if the molecule is e.g. water (H20), we'd have a vector (0, 2, 1) (corresponding to count of C, H, O atoms),
         // and a scalar multiplier (the "x") corresponding to how many of these molecules go into the balanced equation.
@@ -62,49 +498,219 @@ mod test {
 
         // so let's set 4 water molecules and derive the other molecule counts:
         // (bit clunky way to calculate it, for now ok)
-        let free_count = 4.0;
+        let free_count = rational(4, 1);
         // move the c4 vector to right to define the other variables in terms of it
-        let c4_right: Vec<f64> = c4.into_iter().map(|value| value * -1.0).collect();
-        let first_mol_count = c4_right[0] * free_count;
-        assert_eq!(first_mol_count, 1.0);
-        let second_mol_count = c4_right[1] * free_count;
-        assert_eq!(second_mol_count, 5.0);
-        let third_mol_count = c4_right[2] * free_count;
-        assert_eq!(third_mol_count, 3.0);
+        let c4_right: Vec<BigRational> = c4.iter().map(|value| -value.clone()).collect();
+        let first_mol_count = c4_right[0].clone() * free_count.clone();
+        assert_eq!(first_mol_count, rational(1, 1));
+        let second_mol_count = c4_right[1].clone() * free_count.clone();
+        assert_eq!(second_mol_count, rational(5, 1));
+        let third_mol_count = c4_right[2].clone() * free_count;
+        assert_eq!(third_mol_count, rational(3, 1));
 
         // side note: "balanced (chemical) equation" kinda unnecessarily verbose name IMO,
         // balanced means simply that it's a correct equation, if we have incorrect numbers, it's just a false equation.
+
+        // same thing, letting the solver find the free variable:
+        // the solution set is a line through the origin (the null space), spanned by (1/4, 5/4, 3/4, 1)
+        match LinearSystem::homogeneous(coefficients).solve() {
+            Solution::Infinite {
+                null_space_basis, ..
+            } => {
+                assert_eq!(null_space_basis.len(), 1);
+                let counts = null_space_basis[0].clone() * rational(4, 1);
+                assert_eq!(counts.as_slice(), &[1, 5, 3, 4].map(|c| rational(c, 1)));
+            }
+            solution => panic!("expected infinitely many solutions, got: {:?}", solution),
+        }
+
+        // and without picking the free count by hand: smallest positive integers
+        assert_eq!(reaction.balance(), Balance::Unique(ints(&[1, 5, 3, 4])));
     }
 
-    #[derive(Debug)]
-    pub struct ReactionMoleculeUnits {
-        input: Vec<Molecule>,
-        output: Vec<Molecule>,
+    #[test]
+    fn balances_with_smallest_integers() {
+        let reaction = parse_reaction("Ca(OH)2 + H3PO4 -> Ca3(PO4)2 + H2O").unwrap();
+        assert_eq!(reaction.balance(), Balance::Unique(ints(&[3, 2, 1, 6])));
+
+        let reaction = parse_reaction("KMnO4 + HCl -> KCl + MnCl2 + H2O + Cl2").unwrap();
+        assert_eq!(
+            reaction.balance(),
+            Balance::Unique(ints(&[2, 16, 2, 2, 8, 5]))
+        );
     }
 
-    fn as_vec(mol: Molecule) -> Vec<f64> {
-        mol.0.into_iter().map(|e| *e as f64).collect()
+    #[test]
+    fn reports_multiple_balancings() {
+        let reaction = parse_reaction("H2 + O2 -> H2O + H2O2").unwrap();
+
+        let Balance::Multiple(basis) = reaction.balance() else {
+            panic!("expected multiple balancings");
+        };
+        assert_eq!(basis.len(), 2);
+        // each one conserves atoms
+        let a = reaction.composition_matrix();
+        for v in basis {
+            for r in 0..a.nrows() {
+                let reactants: BigInt = (0..2).map(|c| &v[c] * a[(r, c)]).sum();
+                let products: BigInt = (2..4).map(|c| &v[c] * a[(r, c)]).sum();
+                assert_eq!(reactants, products);
+            }
+        }
     }
 
-    fn as_negated_vec(mol: Molecule) -> Vec<f64> {
-        mol.0.into_iter().map(|e| *e as f64 * -1.0).collect()
+    #[test]
+    fn reports_impossible_balancing() {
+        // nothing but 0 H2 -> 0 O2 works
+        let reaction = parse_reaction("H2 -> O2").unwrap();
+        assert_eq!(reaction.balance(), Balance::Impossible);
+
+        // only with 0 O2
+        let reaction = parse_reaction("H2O -> H2O + O2").unwrap();
+        assert_eq!(reaction.balance(), Balance::Impossible);
     }
-    #[derive(Debug, Clone)]
-    struct Molecule(Vector3<f64>);
 
-    struct Mol {
-        o: u32,
-        c: u32,
-        h: u32, //..
+    #[test]
+    fn balances_ions() {
+        let reaction = parse_reaction("Fe^{3+} + Sn^{2+} -> Fe^{2+} + Sn^{4+}").unwrap();
+        assert_eq!(reaction.reactants[0].charge, 3);
+        // the last row is the charge: without it, any amounts of Fe and Sn would balance
+        assert_eq!(
+            reaction
+                .composition_matrix()
+                .row(2)
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            vec![3, 2, 2, 4]
+        );
+        assert_eq!(reaction.balance(), Balance::Unique(ints(&[2, 1, 2, 1])));
     }
 
-    impl Default for Mol {
-        fn default() -> Self {
-            Self { o: 0, c: 0, h: 0 }
-        }
+    #[test]
+    fn balances_half_reaction_in_acidic_medium() {
+        let reaction = parse_reaction("MnO4^- + e^- -> Mn^{2+}").unwrap();
+
+        let (balanced, balance) = reaction.balance_in(Medium::Acidic);
+
+        // MnO4^- + 8H^+ + 5e^- -> Mn^{2+} + 4H2O (in the order: given species, then added ones)
+        assert_eq!(formulas(&balanced.reactants), vec!["MnO4^-", "e^-", "H^+"]);
+        assert_eq!(formulas(&balanced.products), vec!["Mn^{2+}", "H2O"]);
+        assert_eq!(balance, Balance::Unique(ints(&[1, 5, 8, 1, 4])));
+    }
+
+    #[test]
+    fn balances_redox_in_basic_medium() {
+        let reaction = parse_reaction("MnO4^- + I^- -> MnO2 + I2").unwrap();
+
+        let (balanced, balance) = reaction.balance_in(Medium::Basic);
+
+        // 2MnO4^- + 6I^- + 4H2O -> 2MnO2 + 3I2 + 8OH^-
+        assert_eq!(formulas(&balanced.reactants), vec!["MnO4^-", "I^-", "H2O"]);
+        assert_eq!(formulas(&balanced.products), vec!["MnO2", "I2", "OH^-"]);
+        assert_eq!(balance, Balance::Unique(ints(&[2, 6, 4, 2, 3, 8])));
+    }
+
+    #[test]
+    fn parses_charges() {
+        assert_eq!(parse_formula("MnO4^-").unwrap().charge, -1);
+        assert_eq!(parse_formula("Fe^{2+}").unwrap().charge, 2);
+        assert_eq!(parse_formula("Fe^3+").unwrap().charge, 3);
+        assert_eq!(parse_formula("SO4^{-2}").unwrap().charge, -2);
+        assert_eq!(parse_formula("H2O").unwrap().charge, 0);
+
+        let electron = parse_formula("e^-").unwrap();
+        assert!(electron.elements.is_empty());
+        assert_eq!(electron.charge, -1);
+
+        let reaction = parse_reaction("H^+ + OH^- -> H2O").unwrap();
+        assert_eq!(formulas(&reaction.reactants), vec!["H^+", "OH^-"]);
+
+        assert!(parse_formula("Fe^{2+").is_err());
+        assert!(parse_formula("Fe^2").is_err());
+    }
+
+    #[test]
+    fn parses_groups() {
+        let species = parse_formula("Ca(OH)2").unwrap();
+        assert_eq!(species.elements, vec![el("Ca", 1), el("O", 2), el("H", 2)]);
+
+        let species = parse_formula("K4[Fe(CN)6]").unwrap();
+        assert_eq!(
+            species.elements,
+            vec![el("K", 4), el("Fe", 1), el("C", 6), el("N", 6)]
+        );
+    }
+
+    #[test]
+    fn parses_hydrates() {
+        let species = parse_formula("CuSO4·5H2O").unwrap();
+        assert_eq!(
+            species.elements,
+            vec![el("Cu", 1), el("S", 1), el("O", 9), el("H", 10)]
+        );
+        assert_eq!(
+            parse_formula("CuSO4*5H2O").unwrap().elements,
+            species.elements
+        );
+    }
+
+    #[test]
+    fn composition_has_a_row_per_element() {
+        let reaction = parse_reaction("Ca(OH)2 + H3PO4 → Ca3(PO4)2 + H2O").unwrap();
+
+        assert_eq!(reaction.elements(), vec!["Ca", "O", "H", "P"]);
+        #[rustfmt::skip]
+        let expected = DMatrix::from_row_slice(4, 4, &[
+            1, 0, 3, 0,
+            2, 4, 8, 1,
+            2, 3, 0, 2,
+            0, 1, 2, 0,
+        ]);
+        assert_eq!(reaction.composition_matrix(), expected);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(parse_formula("Xx2").unwrap_err().contains("Xx"));
+        assert!(parse_formula("Ca(OH2").is_err());
+        assert!(parse_formula("H2O)").is_err());
+        assert!(parse_formula("h2o").is_err());
+        assert!(parse_reaction("H2 + O2").is_err());
+        assert!(parse_reaction("H2 + -> H2O").is_err());
+    }
+
+    #[test]
+    fn rejects_too_large_counts() {
+        // doesn't fit in u32
+        assert!(parse_formula("H99999999999")
+            .unwrap_err()
+            .contains("too large"));
+        // each fits, but not the product
+        assert!(parse_formula("(H65536)65536").is_err());
+        assert!(parse_formula("CuSO4·65536H65536").is_err());
+        // nor the sum
+        assert!(parse_formula("H4294967295H").is_err());
+    }
+
+    fn el(symbol: &str, count: u32) -> (String, u32) {
+        (symbol.to_string(), count)
+    }
+
+    #[test]
+    fn formats_balanced_reaction() {
+        let reaction = parse_reaction("C3H8 + O2 -> CO2 + H2O").unwrap();
+        assert_eq!(
+            reaction.format_balanced(&ints(&[1, 5, 3, 4])),
+            "C3H8 + 5O2 -> 3CO2 + 4H2O"
+        );
+    }
+
+    fn formulas(species: &[Species]) -> Vec<&str> {
+        species.iter().map(|s| s.formula.as_str()).collect()
     }
 
-    fn to_vector_molecule(m: Mol) -> Molecule {
-        Molecule(Vector3::new(m.c as f64, m.h as f64, m.o as f64))
+    fn ints(values: &[i64]) -> Vec<BigInt> {
+        values.iter().map(|v| BigInt::from(*v)).collect()
     }
 }
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod test {
+    use crate::dense_matrix::{for_each_backend, DenseMatrix};
     use approx::assert_relative_eq;
-    use peroxide::fuga::{matrix, LinearAlgebra, Shape::Col};
 
     fn as_vec(nutrient: Nutrient) -> Vec<f64> {
         vec![nutrient.protein, nutrient.carbs, nutrient.fat]
@@ -17,6 +17,20 @@ mod test {
     // based on an example from Lay's linear algebra
     #[test]
     fn balance_diet() {
+        // written once, run with every backend
+        for (backend, quantities) in for_each_backend!(diet_quantities) {
+            println!(
+                "{}: milk: {}, soy: {}, whey: {}",
+                backend, quantities[0], quantities[1], quantities[2]
+            );
+
+            assert_relative_eq!(quantities[0], 0.277, epsilon = 0.001);
+            assert_relative_eq!(quantities[1], 0.392, epsilon = 0.001);
+            assert_relative_eq!(quantities[2], 0.233, epsilon = 0.001);
+        }
+    }
+
+    fn diet_quantities<M: DenseMatrix>() -> Vec<f64> {
         // some nutrients with their respective macro distributions
         let milk = Nutrient {
             protein: 36.0,
@@ -55,16 +69,11 @@ mod test {
         ]
         .concat()
         .to_owned();
-        let a = matrix(all_cols, 3, 4, Col);
+        let a = M::from_column_slice(3, 4, &all_cols);
 
+        // quantities like 1.1 g fat aren't exact in binary anyway, so we reduce over f64 here
         let rref_a = a.rref();
-        // println!("rref_a: {:?}", rref_a);
-
-        let c4 = rref_a.col(3);
-        println!("result: milk: {}, soy: {}, whey: {}", c4[0], c4[1], c4[2]);
 
-        assert_relative_eq!(c4[0], 0.277, epsilon = 0.001);
-        assert_relative_eq!(c4[1], 0.392, epsilon = 0.001);
-        assert_relative_eq!(c4[2], 0.233, epsilon = 0.001);
+        rref_a.column(3)
     }
 }
@@ -0,0 +1,207 @@
+//! headless subcommands, run instead of the gui when main gets arguments
+
+use crate::balance_chem_eq::{parse_reaction, Balance, Medium, Reaction};
+use crate::row_reduction::rref;
+use nalgebra::DMatrix;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use serde_json::{json, Value};
+use std::fmt::Display;
+
+const USAGE: &str = "usage:
+  linear_alg                  starts the gui
+  linear_alg 3d               starts the gui in 3d (planes of a 3x3 system)
+  linear_alg row-reduction    animates the row reduction of a 2x2 system, one row operation at a time
+  linear_alg dynamics         plots trajectories of a 2d dynamical system x_{k+1} = A x_k
+  linear_alg balance [--json] [--acidic | --basic] \"<reaction>\"
+      e.g. linear_alg balance \"C3H8 + O2 -> CO2 + H2O\"";
+
+/// runs the subcommand in `args` (without the program name), returning what to print
+pub fn run(args: &[String]) -> Result<String, String> {
+    match args.split_first() {
+        Some((command, rest)) if command == "balance" => balance(rest),
+        Some((command, _)) if command == "help" || command == "--help" => Ok(USAGE.to_string()),
+        Some((command, _)) => Err(format!("unknown command '{}'\n{}", command, USAGE)),
+        None => Err(USAGE.to_string()),
+    }
+}
+
+fn balance(args: &[String]) -> Result<String, String> {
+    let mut json = false;
+    let mut medium = None;
+    let mut reaction = None;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--acidic" => medium = Some(Medium::Acidic),
+            "--basic" => medium = Some(Medium::Basic),
+            _ if arg.starts_with("--") => {
+                return Err(format!("unknown option '{}'\n{}", arg, USAGE))
+            }
+            _ if reaction.is_none() => reaction = Some(arg),
+            _ => {
+                return Err(format!(
+                    "expected a single reaction, got also '{}'\n{}",
+                    arg, USAGE
+                ))
+            }
+        }
+    }
+    let input = reaction.ok_or_else(|| format!("missing reaction\n{}", USAGE))?;
+
+    let parsed = parse_reaction(input)?;
+    let (reaction, balance) = match medium {
+        Some(medium) => parsed.balance_in(medium),
+        None => {
+            let balance = parsed.balance();
+            (parsed, balance)
+        }
+    };
+
+    Ok(if json {
+        balance_json(input, &reaction, &balance).to_string()
+    } else {
+        balance_text(&reaction, &balance)
+    })
+}
+
+fn balance_text(reaction: &Reaction, balance: &Balance) -> String {
+    let mut text = match balance {
+        Balance::Unique(coefficients) => {
+            format!("balanced: {}\n", reaction.format_balanced(coefficients))
+        }
+        Balance::Multiple(basis) => {
+            let mut text = "not unique, every balancing is a combination of:\n".to_string();
+            for coefficients in basis {
+                text += &format!("  {}\n", join(coefficients));
+            }
+            text
+        }
+        Balance::Impossible => "can't be balanced\n".to_string(),
+    };
+    let species: Vec<&str> = reaction.species().map(|s| s.formula.as_str()).collect();
+    text += &format!(
+        "\ncomposition matrix (rows: {}, columns: {}):{}",
+        reaction.conserved_quantities().join(", "),
+        species.join(", "),
+        reaction.composition_matrix()
+    );
+    // the products negated, so the balancings are the solutions of Ax = 0
+    text += &format!(
+        "rref (products negated):{}",
+        rref(&reaction.conservation_matrix::<BigRational>()).matrix
+    );
+    text
+}
+
+fn balance_json(input: &str, reaction: &Reaction, balance: &Balance) -> Value {
+    let (status, balanced, coefficients, basis) = match balance {
+        Balance::Unique(coefficients) => (
+            "unique",
+            json!(reaction.format_balanced(coefficients)),
+            integers_json(coefficients),
+            Value::Null,
+        ),
+        Balance::Multiple(basis) => (
+            "multiple",
+            Value::Null,
+            Value::Null,
+            Value::Array(basis.iter().map(|v| integers_json(v)).collect()),
+        ),
+        Balance::Impossible => ("impossible", Value::Null, Value::Null, Value::Null),
+    };
+    let species: Vec<&str> = reaction.species().map(|s| s.formula.as_str()).collect();
+    let reduced = rref(&reaction.conservation_matrix::<BigRational>()).matrix;
+    json!({
+        "reaction": input,
+        "status": status,
+        "balanced": balanced,
+        "coefficients": coefficients,
+        "basis": basis,
+        "species": species,
+        "rows": reaction.conserved_quantities(),
+        "composition_matrix": matrix_json(&reaction.composition_matrix(), |v| json!(v)),
+        // exact fractions, as strings like "-5/4"
+        "rref": matrix_json(&reduced, |v| json!(v.to_string())),
+    })
+}
+
+/// as numbers, unless too big for json numbers
+fn integers_json(values: &[BigInt]) -> Value {
+    Value::Array(
+        values
+            .iter()
+            .map(|v| {
+                v.to_i64()
+                    .map(Value::from)
+                    .unwrap_or_else(|| json!(v.to_string()))
+            })
+            .collect(),
+    )
+}
+
+/// array of rows
+fn matrix_json<T: nalgebra::Scalar, F: Fn(&T) -> Value>(m: &DMatrix<T>, to_json: F) -> Value {
+    Value::Array(
+        m.row_iter()
+            .map(|row| Value::Array(row.iter().map(&to_json).collect()))
+            .collect(),
+    )
+}
+
+fn join<T: Display>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn balances() {
+        let output = run(&args(&["balance", "C3H8 + O2 -> CO2 + H2O"])).unwrap();
+
+        assert!(output.starts_with("balanced: C3H8 + 5O2 -> 3CO2 + 4H2O\n"));
+        assert!(output.contains("rows: C, H, O, columns: C3H8, O2, CO2, H2O"));
+        assert!(output.contains("-1/4"));
+    }
+
+    #[test]
+    fn balances_as_json() {
+        let output = run(&args(&[
+            "balance",
+            "--json",
+            "--acidic",
+            "MnO4^- + e^- -> Mn^{2+}",
+        ]))
+        .unwrap();
+
+        let value: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["status"], "unique");
+        assert_eq!(value["balanced"], "MnO4^- + 5e^- + 8H^+ -> Mn^{2+} + 4H2O");
+        assert_eq!(value["coefficients"], json!([1, 5, 8, 1, 4]));
+        assert_eq!(value["rows"], json!(["Mn", "O", "H", "charge"]));
+        assert_eq!(value["composition_matrix"][3], json!([-1, -1, 1, 2, 0]));
+    }
+
+    #[test]
+    fn reports_usage_errors() {
+        assert!(run(&args(&["unknown"])).unwrap_err().contains("usage"));
+        assert!(run(&args(&["balance"]))
+            .unwrap_err()
+            .contains("missing reaction"));
+        assert!(run(&args(&["balance", "--xml", "H2 -> H2"])).is_err());
+        assert!(run(&args(&["balance", "H2 + O2"]))
+            .unwrap_err()
+            .contains("arrow"));
+    }
+}
@@ -0,0 +1,407 @@
+//! compares every backend against the exact rational solution, for random matrices of different classes.
+//! proptest shrinks failures, so a failing case is reported with the smallest matrix that still shows the difference.
+//!
+//! well conditioned, ill conditioned and rectangular matrices are expected to agree with the exact results.
+//! singular systems are where the backends differ (see alg.rs): there solve and rref have to give one of the outcomes
+//! listed per backend in `documented_singular_outcomes`.
+
+use super::{for_each_backend, DenseMatrix};
+use crate::field::{rational, Field};
+use crate::linear_system::{LinearSystem, Solution};
+use crate::plu::plu;
+use crate::row_reduction::rref;
+use nalgebra::{DMatrix, DVector};
+use num_rational::BigRational;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use std::fmt::{self, Debug, Formatter};
+
+/// integer entries, so the rational reference is exact
+#[derive(Clone)]
+struct Case {
+    a: DMatrix<i64>,
+    b: Vec<i64>,
+}
+
+// printed by proptest for the (shrunk) failing input
+impl Debug for Case {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "A ={}b = {:?}", self.a, self.b)
+    }
+}
+
+impl Case {
+    fn exact_a(&self) -> DMatrix<BigRational> {
+        self.a.map(|v| rational(v, 1))
+    }
+
+    fn exact_b(&self) -> DVector<BigRational> {
+        DVector::from_iterator(self.b.len(), self.b.iter().map(|v| rational(*v, 1)))
+    }
+
+    fn float_a<M: DenseMatrix>(&self) -> M {
+        M::from_dmatrix(&self.a.map(|v| v as f64))
+    }
+
+    fn float_b(&self) -> Vec<f64> {
+        self.b.iter().map(|v| *v as f64).collect()
+    }
+}
+
+/// strictly diagonally dominant, so invertible and well conditioned
+fn well_conditioned() -> impl Strategy<Value = Case> {
+    (2..=4usize)
+        .prop_flat_map(|n| (vec(-5..=5i64, n * n), vec(1..=5i64, n), vec(-9..=9i64, n)))
+        .prop_map(|(entries, margins, b)| {
+            let n = b.len();
+            let mut a = DMatrix::from_row_slice(n, n, &entries);
+            for r in 0..n {
+                let off_diagonal: i64 = (0..n).filter(|&c| c != r).map(|c| a[(r, c)].abs()).sum();
+                a[(r, r)] = off_diagonal + margins[r];
+            }
+            Case { a, b }
+        })
+}
+
+/// a well conditioned matrix with 1000 x the first row added to the last:
+/// same determinant, but the last row is almost parallel to the first (condition number ~10^6)
+fn ill_conditioned() -> impl Strategy<Value = Case> {
+    well_conditioned().prop_map(|mut case| {
+        let last = case.a.nrows() - 1;
+        for c in 0..case.a.ncols() {
+            case.a[(last, c)] += 1000 * case.a[(0, c)];
+        }
+        case
+    })
+}
+
+/// the last row is a combination of the first two. depending on b there are no or infinitely many solutions
+fn singular() -> impl Strategy<Value = Case> {
+    (3..=4usize)
+        .prop_flat_map(|n| {
+            (
+                vec(-9..=9i64, n * n),
+                vec(-9..=9i64, n),
+                -3..=3i64,
+                -3..=3i64,
+                any::<bool>(),
+            )
+        })
+        .prop_map(|(entries, mut b, c1, c2, consistent)| {
+            let n = b.len();
+            let mut a = DMatrix::from_row_slice(n, n, &entries);
+            for c in 0..n {
+                a[(n - 1, c)] = c1 * a[(0, c)] + c2 * a[(1, c)];
+            }
+            if consistent {
+                b[n - 1] = c1 * b[0] + c2 * b[1];
+            }
+            Case { a, b }
+        })
+}
+
+fn rectangular() -> impl Strategy<Value = Case> {
+    (1..=4usize, 1..=4usize)
+        .prop_filter("not square", |(rows, cols)| rows != cols)
+        .prop_flat_map(|(rows, cols)| {
+            (
+                Just(rows),
+                vec(-9..=9i64, rows * cols),
+                vec(-9..=9i64, rows),
+            )
+        })
+        .prop_map(|(rows, entries, b)| Case {
+            a: DMatrix::from_row_slice(rows, entries.len() / rows, &entries),
+            b,
+        })
+}
+
+fn max_abs_difference(x: &[f64], y: &[f64]) -> f64 {
+    x.iter()
+        .zip(y)
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f64::max)
+}
+
+fn max_abs(x: &[f64]) -> f64 {
+    x.iter().map(|v| v.abs()).fold(0.0, f64::max)
+}
+
+/// compares the backend's solution with the exact solution set. Err describes the difference
+fn check_solve<M: DenseMatrix>(case: &Case, tolerance: f64) -> Result<(), String> {
+    let a = case.float_a::<M>();
+    let b = case.float_b();
+    let solution = a.solve(&b);
+    match (
+        LinearSystem::new(case.exact_a(), case.exact_b()).solve(),
+        solution,
+    ) {
+        (Solution::Unique(exact), Some(x)) => {
+            let exact: Vec<f64> = exact.iter().map(|v| v.to_f64()).collect();
+            let error = max_abs_difference(&exact, &x);
+            if error > tolerance * (1.0 + max_abs(&exact)) {
+                return Err(format!("solution {:?}, expected {:?}", x, exact));
+            }
+        }
+        (Solution::Unique(_), None) => return Err("no solution, expected a unique one".into()),
+        (Solution::Inconsistent, Some(x)) => {
+            return Err(format!("solution {:?} for an inconsistent system", x))
+        }
+        (Solution::Inconsistent, None) => {}
+        // any solution is fine, as long as it's one
+        (Solution::Infinite { .. }, Some(x)) => {
+            let residual = max_abs_difference(&a.mul_vector(&x), &b);
+            if residual.is_nan() || residual > tolerance * (1.0 + max_abs(&b)) {
+                return Err(format!(
+                    "{:?} doesn't solve the system (residual {})",
+                    x, residual
+                ));
+            }
+        }
+        (Solution::Infinite { .. }, None) => {}
+    }
+    Ok(())
+}
+
+fn check_det<M: DenseMatrix>(case: &Case, tolerance: f64) -> Result<(), String> {
+    let exact = plu(&case.exact_a()).determinant().map(|d| d.to_f64());
+    let det = case.float_a::<M>().det();
+    match (exact, det) {
+        (Some(exact), Some(det)) => {
+            // relative to the largest possible determinant (Hadamard's bound), since for singular matrices it's 0
+            let bound: f64 = case
+                .a
+                .row_iter()
+                .map(|row| row.iter().map(|v| (v * v) as f64).sum::<f64>().sqrt())
+                .product();
+            if (exact - det).abs() > tolerance * bound.max(1.0) {
+                return Err(format!("determinant {}, expected {}", det, exact));
+            }
+        }
+        (None, None) => {}
+        (exact, det) => return Err(format!("determinant {:?}, expected {:?}", det, exact)),
+    }
+    Ok(())
+}
+
+fn check_rref<M: DenseMatrix>(case: &Case, tolerance: f64) -> Result<(), String> {
+    let exact = rref(&case.exact_a()).matrix.map(|v| v.to_f64());
+    let reduced = case.float_a::<M>().rref().to_dmatrix();
+    if max_abs_difference(exact.as_slice(), reduced.as_slice()) > tolerance {
+        return Err(format!("rref{}expected{}", reduced, exact));
+    }
+    Ok(())
+}
+
+/// all checks for one backend
+fn check<M: DenseMatrix>(case: &Case, tolerance: f64) -> Result<(), String> {
+    let mut errors = vec![];
+    if case.a.nrows() == case.a.ncols() {
+        errors.extend(check_solve::<M>(case, tolerance).err());
+    }
+    errors.extend(check_det::<M>(case, tolerance).err());
+    errors.extend(check_rref::<M>(case, tolerance).err());
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+/// what a backend's solve returned for a singular system
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SingularSolve {
+    NoSolution,
+    /// inf / NaN
+    NonFinite,
+    /// one of the exact solutions (within the tolerance), only possible for consistent systems
+    Solution,
+    /// finite, not a solution and larger than `BLOWUP`
+    Blowup,
+    Other,
+}
+
+/// what a backend's rref returned for a singular matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SingularRref {
+    /// the exact rref (within the tolerance)
+    Exact,
+    /// more non zero rows than the exact rank: a rounding error was used as pivot
+    RoundingPivot,
+    Other,
+}
+
+/// our matrices have small integer entries, so their solutions are nowhere near this.
+/// we only get such values by dividing by a rounding error
+const BLOWUP: f64 = 1e8;
+
+/// the outcomes of a backend for singular systems that we know and can explain
+struct SingularOutcomes {
+    solve: &'static [SingularSolve],
+    rref: &'static [SingularRref],
+}
+
+/// in floating point a singular matrix rarely stays exactly singular: after subtracting rows,
+/// where the exact pivot is 0 there's usually a rounding error (~1e-16) left.
+/// backends that only treat an exact 0 as singular then divide by it, giving a blowup,
+/// or (with an exact 0) what they do for singular matrices. for consistent systems they can also find a solution.
+/// the determinant is still close to 0 for all of them, and our own rref uses a tolerance
+fn documented_singular_outcomes<M: DenseMatrix>() -> SingularOutcomes {
+    use SingularRref::{Exact, RoundingPivot};
+    use SingularSolve::{Blowup, NoSolution, NonFinite};
+    match M::NAME {
+        // LU solve returns None for an exact 0 pivot (see `nalgebra_solves_inconsistent_system`)
+        "nalgebra" => SingularOutcomes {
+            solve: &[NoSolution, Blowup],
+            rref: &[Exact],
+        },
+        // solving with the LU never fails, with an exact 0 pivot we get inf / NaN
+        "faer" => SingularOutcomes {
+            solve: &[NonFinite, Blowup],
+            rref: &[Exact],
+        },
+        // LAPACK's gesv reports exact 0 pivots
+        "ndarray" => SingularOutcomes {
+            solve: &[NoSolution, Blowup],
+            rref: &[Exact],
+        },
+        // we filter out inf / NaN (as None), but not huge finite values. its rref only skips exact 0 pivots
+        "peroxide" => SingularOutcomes {
+            solve: &[NoSolution, Blowup],
+            rref: &[Exact, RoundingPivot],
+        },
+        _ => SingularOutcomes {
+            solve: &[],
+            rref: &[Exact],
+        },
+    }
+}
+
+fn classify_singular_solve<M: DenseMatrix>(
+    case: &Case,
+    consistent: bool,
+    tolerance: f64,
+) -> (SingularSolve, Option<Vec<f64>>) {
+    let a = case.float_a::<M>();
+    let b = case.float_b();
+    let Some(x) = a.solve(&b) else {
+        return (SingularSolve::NoSolution, None);
+    };
+    let outcome = if x.iter().any(|v| !v.is_finite()) {
+        SingularSolve::NonFinite
+    } else if consistent
+        && max_abs_difference(&a.mul_vector(&x), &b) <= tolerance * (1.0 + max_abs(&b))
+    {
+        SingularSolve::Solution
+    } else if max_abs(&x) > BLOWUP {
+        SingularSolve::Blowup
+    } else {
+        SingularSolve::Other
+    };
+    (outcome, Some(x))
+}
+
+fn classify_singular_rref<M: DenseMatrix>(case: &Case, tolerance: f64) -> SingularRref {
+    let exact = rref(&case.exact_a());
+    let reduced = case.float_a::<M>().rref().to_dmatrix();
+    let non_zero_rows = reduced
+        .row_iter()
+        .filter(|row| row.iter().any(|v| v.abs() > tolerance))
+        .count();
+    if max_abs_difference(
+        exact.matrix.map(|v| v.to_f64()).as_slice(),
+        reduced.as_slice(),
+    ) <= tolerance
+    {
+        SingularRref::Exact
+    } else if non_zero_rows > exact.rank() {
+        SingularRref::RoundingPivot
+    } else {
+        SingularRref::Other
+    }
+}
+
+/// the determinant like for other matrices, solve and rref have to give one of the documented outcomes
+fn check_singular<M: DenseMatrix>(case: &Case, tolerance: f64) -> Result<(), String> {
+    let documented = documented_singular_outcomes::<M>();
+    let consistent =
+        LinearSystem::new(case.exact_a(), case.exact_b()).solve() != Solution::Inconsistent;
+    let mut errors = vec![];
+    let (solve, x) = classify_singular_solve::<M>(case, consistent, tolerance);
+    if !(documented.solve.contains(&solve) || solve == SingularSolve::Solution) {
+        errors.push(format!(
+            "solve: {:?} {:?}, documented {:?} (consistent: {})",
+            solve, x, documented.solve, consistent
+        ));
+    }
+    errors.extend(check_det::<M>(case, tolerance).err());
+    let rref = classify_singular_rref::<M>(case, tolerance);
+    if !documented.rref.contains(&rref) {
+        errors.push(format!(
+            "rref: {:?}, documented {:?}",
+            rref, documented.rref
+        ));
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+/// fails (listing each backend that differs) if any backend differs from the exact results
+fn check_all_backends(case: &Case, tolerance: f64) -> Result<(), TestCaseError> {
+    report_failures(for_each_backend!(check, case, tolerance))
+}
+
+fn report_failures(results: Vec<(&str, Result<(), String>)>) -> Result<(), TestCaseError> {
+    let failures: Vec<String> = results
+        .into_iter()
+        .filter_map(|(backend, result)| result.err().map(|e| format!("{}: {}", backend, e)))
+        .collect();
+    prop_assert!(failures.is_empty(), "{}", failures.join("\n"));
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn well_conditioned_matches_exact(case in well_conditioned()) {
+        check_all_backends(&case, 1e-9)?;
+    }
+
+    #[test]
+    fn ill_conditioned_matches_exact(case in ill_conditioned()) {
+        check_all_backends(&case, 1e-6)?;
+    }
+
+    #[test]
+    fn singular_gives_documented_outcomes(case in singular()) {
+        report_failures(for_each_backend!(check_singular, &case, 1e-6))?;
+    }
+
+    #[test]
+    fn rectangular_matches_exact(case in rectangular()) {
+        check_all_backends(&case, 1e-9)?;
+    }
+}
+
+/// why nalgebra's solve can blow up: the minimal case proptest found
+#[test]
+fn nalgebra_solves_inconsistent_system() {
+    let case = Case {
+        a: DMatrix::from_row_slice(3, 3, &[0, 1, -1, 1, 0, 1, -3, -1, -2]),
+        b: vec![0, 0, -1],
+    };
+    assert_eq!(
+        LinearSystem::new(case.exact_a(), case.exact_b()).solve(),
+        Solution::Inconsistent
+    );
+
+    let (outcome, x) = classify_singular_solve::<DMatrix<f64>>(&case, false, 1e-6);
+    assert_eq!(outcome, SingularSolve::Blowup);
+    // 1 / (rounding error)
+    assert!(x.unwrap().iter().all(|v| v.abs() > 1e15));
+    // the determinant and rref still show that it's singular
+    assert!(check_singular::<DMatrix<f64>>(&case, 1e-6).is_ok());
+    assert!(check::<DMatrix<f64>>(&case, 1e-6).is_err());
+}
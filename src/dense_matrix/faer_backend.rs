@@ -0,0 +1,81 @@
+use super::{DenseMatrix, Lu, Qr, Svd};
+use crate::row_reduction::rref;
+use faer::prelude::SpSolver;
+use faer::Mat;
+
+impl DenseMatrix for Mat<f64> {
+    const NAME: &'static str = "faer";
+
+    fn from_fn<F: Fn(usize, usize) -> f64>(rows: usize, cols: usize, f: F) -> Self {
+        Mat::from_fn(rows, cols, f)
+    }
+
+    fn nrows(&self) -> usize {
+        self.nrows()
+    }
+
+    fn ncols(&self) -> usize {
+        self.ncols()
+    }
+
+    fn get(&self, row: usize, col: usize) -> f64 {
+        self.read(row, col)
+    }
+
+    fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+        if self.nrows() != self.ncols() {
+            return None;
+        }
+        let rhs = Mat::from_fn(b.len(), 1, |r, _| b[r]);
+        let x = self.partial_piv_lu().solve(&rhs);
+        Some((0..x.nrows()).map(|r| x.read(r, 0)).collect())
+    }
+
+    // faer has no rref, so we use ours
+    fn rref(&self) -> Self {
+        Self::from_dmatrix(&rref(&self.to_dmatrix()).matrix)
+    }
+
+    fn lu(&self) -> Lu<Self> {
+        let lu = self.partial_piv_lu();
+        // row i of PA is row forward[i] of A
+        let (forward, _) = lu.row_permutation().arrays();
+        let n = self.nrows();
+        Lu {
+            p: Mat::from_fn(n, n, |r, c| if forward[r] == c { 1.0 } else { 0.0 }),
+            q: Mat::identity(self.ncols(), self.ncols()),
+            l: lu.compute_l(),
+            u: lu.compute_u(),
+        }
+    }
+
+    fn qr(&self) -> Qr<Self> {
+        let qr = self.qr();
+        Qr {
+            q: qr.compute_q(),
+            r: qr.compute_r(),
+        }
+    }
+
+    fn svd(&self) -> Svd<Self> {
+        let svd = self.svd();
+        let s = svd.s_diagonal();
+        Svd {
+            u: svd.u().to_owned(),
+            singular_values: (0..s.nrows()).map(|i| s.read(i)).collect(),
+            v_t: svd.v().transpose().to_owned(),
+        }
+    }
+
+    fn det(&self) -> Option<f64> {
+        (self.nrows() == self.ncols()).then(|| self.determinant())
+    }
+
+    // faer returns inf / NaN for singular matrices instead of failing, so we check the determinant first
+    fn inverse(&self) -> Option<Self> {
+        match self.det() {
+            Some(det) if det != 0.0 => Some(self.partial_piv_lu().inverse()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! one interface over the linear algebra libraries we've been trying out (see alg.rs),
+//! so code like the Lay examples can be written once and run (and compared) with each of them.
+//! only built for tests, the application code uses our own (exact) algorithms.
+//!
+//! nalgebra is always available, the others are behind cargo features:
+//! `faer` and `peroxide` (on by default), and `ndarray`, which additionally needs a LAPACK backend
+//! for ndarray-linalg (e.g. `ndarray-linalg/openblas-static`) to link.
+
+use nalgebra::DMatrix;
+
+#[cfg(test)]
+mod differential_test;
+#[cfg(feature = "faer")]
+mod faer_backend;
+mod nalgebra_backend;
+#[cfg(feature = "ndarray")]
+mod ndarray_backend;
+#[cfg(feature = "peroxide")]
+mod peroxide_backend;
+
+/// P A Q = L U
+/// Q is the identity, except for backends that also swap columns (peroxide)
+#[derive(Debug, Clone)]
+pub struct Lu<M> {
+    pub p: M,
+    pub q: M,
+    pub l: M,
+    pub u: M,
+}
+
+/// A = Q R
+#[derive(Debug, Clone)]
+pub struct Qr<M> {
+    pub q: M,
+    pub r: M,
+}
+
+/// A = U Σ Vᵀ
+#[derive(Debug, Clone)]
+pub struct Svd<M> {
+    pub u: M,
+    pub singular_values: Vec<f64>,
+    pub v_t: M,
+}
+
+/// a dense f64 matrix from one of the backends
+///
+/// operations return what the backend returns, we don't try to fix up its results:
+/// e.g. `solve` on a singular matrix may return None with one backend and huge numbers with another,
+/// which is exactly what we want to see when comparing them.
+pub trait DenseMatrix: Sized + Clone {
+    /// name for messages, e.g. when reporting which backend differs
+    const NAME: &'static str;
+
+    fn from_fn<F: Fn(usize, usize) -> f64>(rows: usize, cols: usize, f: F) -> Self;
+
+    fn nrows(&self) -> usize;
+
+    fn ncols(&self) -> usize;
+
+    fn get(&self, row: usize, col: usize) -> f64;
+
+    /// solution of Ax = b, None if the backend reports that there's none or it can't solve (e.g. non square)
+    fn solve(&self, b: &[f64]) -> Option<Vec<f64>>;
+
+    fn rref(&self) -> Self;
+
+    fn lu(&self) -> Lu<Self>;
+
+    fn qr(&self) -> Qr<Self>;
+
+    fn svd(&self) -> Svd<Self>;
+
+    /// None for non square matrices
+    fn det(&self) -> Option<f64>;
+
+    /// None for non square or (according to the backend) singular matrices
+    fn inverse(&self) -> Option<Self>;
+
+    fn from_row_slice(rows: usize, cols: usize, values: &[f64]) -> Self {
+        assert_eq!(values.len(), rows * cols);
+        Self::from_fn(rows, cols, |r, c| values[r * cols + c])
+    }
+
+    fn from_column_slice(rows: usize, cols: usize, values: &[f64]) -> Self {
+        assert_eq!(values.len(), rows * cols);
+        Self::from_fn(rows, cols, |r, c| values[c * rows + r])
+    }
+
+    fn identity(n: usize) -> Self {
+        Self::from_fn(n, n, |r, c| if r == c { 1.0 } else { 0.0 })
+    }
+
+    fn column(&self, col: usize) -> Vec<f64> {
+        (0..self.nrows()).map(|r| self.get(r, col)).collect()
+    }
+
+    fn mul_vector(&self, x: &[f64]) -> Vec<f64> {
+        assert_eq!(x.len(), self.ncols());
+        (0..self.nrows())
+            .map(|r| (0..self.ncols()).map(|c| self.get(r, c) * x[c]).sum())
+            .collect()
+    }
+
+    /// common representation, to compare backends or use our own algorithms on the data
+    fn to_dmatrix(&self) -> DMatrix<f64> {
+        DMatrix::from_fn(self.nrows(), self.ncols(), |r, c| self.get(r, c))
+    }
+
+    fn from_dmatrix(m: &DMatrix<f64>) -> Self {
+        Self::from_fn(m.nrows(), m.ncols(), |r, c| m[(r, c)])
+    }
+}
+
+/// runs a generic function once per enabled backend, collecting `(backend name, result)`
+/// e.g. `for_each_backend!(diet_quantities)` calls `diet_quantities::<DMatrix<f64>>()`, `diet_quantities::<faer::Mat<f64>>()`, ..
+/// arguments are passed on to every call: `for_each_backend!(check, &a, &b)`
+macro_rules! for_each_backend {
+    ($f:ident $(, $arg:expr)*) => {{
+        let results = std::iter::once((
+            <nalgebra::DMatrix<f64> as $crate::dense_matrix::DenseMatrix>::NAME,
+            $f::<nalgebra::DMatrix<f64>>($($arg),*),
+        ));
+        #[cfg(feature = "faer")]
+        let results = results.chain(std::iter::once((
+            <faer::Mat<f64> as $crate::dense_matrix::DenseMatrix>::NAME,
+            $f::<faer::Mat<f64>>($($arg),*),
+        )));
+        #[cfg(feature = "ndarray")]
+        let results = results.chain(std::iter::once((
+            <ndarray::Array2<f64> as $crate::dense_matrix::DenseMatrix>::NAME,
+            $f::<ndarray::Array2<f64>>($($arg),*),
+        )));
+        #[cfg(feature = "peroxide")]
+        let results = results.chain(std::iter::once((
+            <peroxide::fuga::Matrix as $crate::dense_matrix::DenseMatrix>::NAME,
+            $f::<peroxide::fuga::Matrix>($($arg),*),
+        )));
+        results.collect::<Vec<_>>()
+    }};
+}
+pub(crate) use for_each_backend;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use approx::assert_relative_eq;
+    use nalgebra::DVector;
+
+    fn sample<M: DenseMatrix>() -> M {
+        #[rustfmt::skip]
+        let m = M::from_row_slice(3, 3, &[
+            2.0, 1.0, 3.0,
+            1.0, -1.0, 2.0,
+            3.0, 2.0, 1.0,
+        ]);
+        m
+    }
+
+    fn solve_sample<M: DenseMatrix>() -> Option<Vec<f64>> {
+        sample::<M>().solve(&[5.0, 4.0, 7.0])
+    }
+
+    fn det_sample<M: DenseMatrix>() -> Option<f64> {
+        sample::<M>().det()
+    }
+
+    /// P A Q and L U, to compare
+    fn lu_sample<M: DenseMatrix>() -> (DMatrix<f64>, DMatrix<f64>) {
+        let a = sample::<M>();
+        let lu = a.lu();
+        (
+            lu.p.to_dmatrix() * a.to_dmatrix() * lu.q.to_dmatrix(),
+            lu.l.to_dmatrix() * lu.u.to_dmatrix(),
+        )
+    }
+
+    /// A and Q R
+    fn qr_sample<M: DenseMatrix>() -> (DMatrix<f64>, DMatrix<f64>) {
+        let a = sample::<M>();
+        let qr = a.qr();
+        (a.to_dmatrix(), qr.q.to_dmatrix() * qr.r.to_dmatrix())
+    }
+
+    /// A and U Σ Vᵀ
+    fn svd_sample<M: DenseMatrix>() -> (DMatrix<f64>, DMatrix<f64>) {
+        let a = sample::<M>();
+        let svd = a.svd();
+        let sigma = DMatrix::from_diagonal(&svd.singular_values.clone().into());
+        (
+            a.to_dmatrix(),
+            svd.u.to_dmatrix() * sigma * svd.v_t.to_dmatrix(),
+        )
+    }
+
+    /// A A^-1 and I
+    fn inverse_sample<M: DenseMatrix>() -> (DMatrix<f64>, DMatrix<f64>) {
+        let a = sample::<M>();
+        (
+            a.to_dmatrix() * a.inverse().unwrap().to_dmatrix(),
+            M::identity(3).to_dmatrix(),
+        )
+    }
+
+    fn rref_sample<M: DenseMatrix>() -> DMatrix<f64> {
+        #[rustfmt::skip]
+        let m = M::from_row_slice(3, 4, &[
+            1.0, 1.0, 1.0, 2.0,
+            2.0, -1.0, 2.0, 7.0,
+            -1.0, -2.0, 3.0, 7.0,
+        ]);
+        m.rref().to_dmatrix()
+    }
+
+    #[test]
+    fn solve() {
+        for (name, x) in for_each_backend!(solve_sample) {
+            let x = x.unwrap_or_else(|| panic!("{} found no solution", name));
+            assert_relative_eq!(
+                DVector::from_vec(x),
+                DVector::from_row_slice(&[3.0, -1.0, 0.0]),
+                epsilon = 1e-10
+            );
+        }
+    }
+
+    #[test]
+    fn determinant() {
+        for (_, det) in for_each_backend!(det_sample) {
+            assert_relative_eq!(det.unwrap(), 10.0, epsilon = 1e-10);
+        }
+    }
+
+    #[test]
+    fn factorizations_reproduce_a() {
+        for (_, (paq, lu)) in for_each_backend!(lu_sample) {
+            assert_relative_eq!(paq, lu, epsilon = 1e-10);
+        }
+        for (_, (a, qr)) in for_each_backend!(qr_sample) {
+            assert_relative_eq!(a, qr, epsilon = 1e-10);
+        }
+        for (_, (a, svd)) in for_each_backend!(svd_sample) {
+            assert_relative_eq!(a, svd, epsilon = 1e-10);
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        for (_, (product, identity)) in for_each_backend!(inverse_sample) {
+            assert_relative_eq!(product, identity, epsilon = 1e-10);
+        }
+    }
+
+    #[test]
+    fn rref() {
+        #[rustfmt::skip]
+        let expected = DMatrix::from_row_slice(3, 4, &[
+            1.0, 0.0, 0.0, 1.0,
+            0.0, 1.0, 0.0, -1.0,
+            0.0, 0.0, 1.0, 2.0,
+        ]);
+        for (_, reduced) in for_each_backend!(rref_sample) {
+            assert_relative_eq!(reduced, expected, epsilon = 1e-10);
+        }
+    }
+}
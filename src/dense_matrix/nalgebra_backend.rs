@@ -0,0 +1,75 @@
+use super::{DenseMatrix, Lu, Qr, Svd};
+use crate::row_reduction::rref;
+use nalgebra::{DMatrix, DVector};
+
+impl DenseMatrix for DMatrix<f64> {
+    const NAME: &'static str = "nalgebra";
+
+    fn from_fn<F: Fn(usize, usize) -> f64>(rows: usize, cols: usize, f: F) -> Self {
+        DMatrix::from_fn(rows, cols, f)
+    }
+
+    fn nrows(&self) -> usize {
+        self.nrows()
+    }
+
+    fn ncols(&self) -> usize {
+        self.ncols()
+    }
+
+    fn get(&self, row: usize, col: usize) -> f64 {
+        self[(row, col)]
+    }
+
+    fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+        if !self.is_square() {
+            return None;
+        }
+        self.clone()
+            .lu()
+            .solve(&DVector::from_row_slice(b))
+            .map(|x| x.as_slice().to_vec())
+    }
+
+    // nalgebra has no rref, so we use ours
+    fn rref(&self) -> Self {
+        rref(self).matrix
+    }
+
+    fn lu(&self) -> Lu<Self> {
+        let (permutation, l, u) = self.clone().lu().unpack();
+        let mut p = DMatrix::identity(self.nrows(), self.nrows());
+        permutation.permute_rows(&mut p);
+        Lu {
+            p,
+            q: DMatrix::identity(self.ncols(), self.ncols()),
+            l,
+            u,
+        }
+    }
+
+    fn qr(&self) -> Qr<Self> {
+        let qr = self.clone().qr();
+        Qr {
+            q: qr.q(),
+            r: qr.r(),
+        }
+    }
+
+    fn svd(&self) -> Svd<Self> {
+        let svd = self.clone().svd(true, true);
+        Svd {
+            u: svd.u.expect("requested u"),
+            singular_values: svd.singular_values.as_slice().to_vec(),
+            v_t: svd.v_t.expect("requested v_t"),
+        }
+    }
+
+    fn det(&self) -> Option<f64> {
+        self.is_square().then(|| self.determinant())
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        self.clone().try_inverse()
+    }
+}
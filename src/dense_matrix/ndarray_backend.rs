@@ -0,0 +1,78 @@
+use super::{DenseMatrix, Lu, Qr, Svd};
+use crate::plu::plu;
+use crate::row_reduction::rref;
+use ndarray::{Array1, Array2};
+use ndarray_linalg::{Determinant, Inverse, Solve, QR, SVD};
+
+impl DenseMatrix for Array2<f64> {
+    const NAME: &'static str = "ndarray";
+
+    fn from_fn<F: Fn(usize, usize) -> f64>(rows: usize, cols: usize, f: F) -> Self {
+        Array2::from_shape_fn((rows, cols), |(r, c)| f(r, c))
+    }
+
+    fn nrows(&self) -> usize {
+        self.nrows()
+    }
+
+    fn ncols(&self) -> usize {
+        self.ncols()
+    }
+
+    fn get(&self, row: usize, col: usize) -> f64 {
+        self[(row, col)]
+    }
+
+    fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+        if !self.is_square() {
+            return None;
+        }
+        Solve::solve(self, &Array1::from(b.to_vec()))
+            .ok()
+            .map(|x| x.to_vec())
+    }
+
+    // ndarray-linalg has no rref, so we use ours
+    fn rref(&self) -> Self {
+        Self::from_dmatrix(&rref(&self.to_dmatrix()).matrix)
+    }
+
+    // ndarray-linalg only exposes LAPACK's packed factorization, so we use our PLU
+    fn lu(&self) -> Lu<Self> {
+        let plu = plu(&self.to_dmatrix());
+        Lu {
+            p: Self::from_dmatrix(&plu.p),
+            q: Self::identity(self.ncols()),
+            l: Self::from_dmatrix(&plu.l),
+            u: Self::from_dmatrix(&plu.u),
+        }
+    }
+
+    fn qr(&self) -> Qr<Self> {
+        let (q, r) = QR::qr(self).expect("qr failed");
+        Qr { q, r }
+    }
+
+    fn svd(&self) -> Svd<Self> {
+        let (u, s, v_t) = SVD::svd(self, true, true).expect("svd failed");
+        Svd {
+            u: u.expect("requested u"),
+            singular_values: s.to_vec(),
+            v_t: v_t.expect("requested v_t"),
+        }
+    }
+
+    fn det(&self) -> Option<f64> {
+        if !self.is_square() {
+            return None;
+        }
+        Determinant::det(self).ok()
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        if !self.is_square() {
+            return None;
+        }
+        Inverse::inv(self).ok()
+    }
+}
@@ -0,0 +1,86 @@
+use super::{DenseMatrix, Lu, Qr, Svd};
+use peroxide::fuga::{matrix, LinearAlgebra, Matrix, Shape::Row, SolveKind};
+
+impl DenseMatrix for Matrix {
+    const NAME: &'static str = "peroxide";
+
+    fn from_fn<F: Fn(usize, usize) -> f64>(rows: usize, cols: usize, f: F) -> Self {
+        let values = (0..rows)
+            .flat_map(|r| (0..cols).map(move |c| (r, c)))
+            .map(|(r, c)| f(r, c))
+            .collect();
+        matrix(values, rows, cols, Row)
+    }
+
+    fn nrows(&self) -> usize {
+        self.row
+    }
+
+    fn ncols(&self) -> usize {
+        self.col
+    }
+
+    fn get(&self, row: usize, col: usize) -> f64 {
+        self[(row, col)]
+    }
+
+    // peroxide returns NaN / inf when there's no solution, we report that as None
+    fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+        if self.row != self.col {
+            return None;
+        }
+        let x = LinearAlgebra::solve(self, &b.to_vec(), SolveKind::LU);
+        x.iter().all(|v| v.is_finite()).then_some(x)
+    }
+
+    fn rref(&self) -> Self {
+        LinearAlgebra::rref(self)
+    }
+
+    // peroxide pivots both rows and columns: P A Q = L U
+    // p and q are the swaps made during the elimination, in order
+    fn lu(&self) -> Lu<Self> {
+        let lu = LinearAlgebra::lu(self);
+        Lu {
+            p: swaps_to_permutation(self.row, &lu.p),
+            q: swaps_to_permutation(self.col, &lu.q).t(),
+            l: lu.l,
+            u: lu.u,
+        }
+    }
+
+    fn qr(&self) -> Qr<Self> {
+        let qr = LinearAlgebra::qr(self);
+        Qr { q: qr.q, r: qr.r }
+    }
+
+    fn svd(&self) -> Svd<Self> {
+        let svd = LinearAlgebra::svd(self);
+        Svd {
+            u: svd.u,
+            singular_values: svd.s,
+            v_t: svd.vt,
+        }
+    }
+
+    fn det(&self) -> Option<f64> {
+        (self.row == self.col).then(|| LinearAlgebra::det(self))
+    }
+
+    // like solve, peroxide doesn't fail for singular matrices, so we check the determinant first
+    fn inverse(&self) -> Option<Self> {
+        match self.det() {
+            Some(det) if det != 0.0 => Some(LinearAlgebra::inv(self)),
+            _ => None,
+        }
+    }
+}
+
+/// permutation matrix for a sequence of row swaps, applied in order to the identity
+fn swaps_to_permutation(n: usize, swaps: &[(usize, usize)]) -> Matrix {
+    let mut order: Vec<usize> = (0..n).collect();
+    for &(a, b) in swaps {
+        order.swap(a, b);
+    }
+    <Matrix as DenseMatrix>::from_fn(n, n, |r, c| if order[r] == c { 1.0 } else { 0.0 })
+}
@@ -0,0 +1,348 @@
+use crate::nnls::nnls;
+use crate::simplex::{Constraint, LinearProgram, LpSolution, Relation};
+use nalgebra::{DMatrix, DVector};
+
+/// a food with its cost and nutrients per unit (e.g. per 100g), nutrients in the order of the diet's targets
+#[derive(Debug, Clone, PartialEq)]
+pub struct Food {
+    pub name: String,
+    pub cost: f64,
+    pub nutrients: Vec<f64>,
+}
+
+/// how much of a nutrient the diet needs in total. min == max for an exact amount
+#[derive(Debug, Clone, PartialEq)]
+pub struct NutrientTarget {
+    pub name: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diet {
+    pub foods: Vec<Food>,
+    pub targets: Vec<NutrientTarget>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DietPlan {
+    /// the cheapest amounts (all >= 0) meeting every target
+    Optimal {
+        quantities: Vec<f64>,
+        cost: f64,
+        /// the resulting total of each nutrient
+        totals: Vec<f64>,
+    },
+    /// constraints like "protein >= 33" that can't be met together with non-negative amounts
+    Infeasible { conflicts: Vec<String> },
+}
+
+/// the non-negative amounts that come closest to the targets, for when they can't be met exactly
+#[derive(Debug, Clone, PartialEq)]
+pub struct DietFit {
+    pub quantities: Vec<f64>,
+    pub cost: f64,
+    pub totals: Vec<f64>,
+    /// total - target, per nutrient
+    pub residuals: Vec<f64>,
+    /// residual as a percentage of the target. None where the target is 0
+    pub deviations: Vec<Option<f64>>,
+}
+
+impl Food {
+    pub fn new(name: &str, cost: f64, nutrients: Vec<f64>) -> Food {
+        Food {
+            name: name.to_string(),
+            cost,
+            nutrients,
+        }
+    }
+}
+
+impl NutrientTarget {
+    pub fn exactly(name: &str, amount: f64) -> NutrientTarget {
+        NutrientTarget::between(name, Some(amount), Some(amount))
+    }
+
+    pub fn between(name: &str, min: Option<f64>, max: Option<f64>) -> NutrientTarget {
+        NutrientTarget {
+            name: name.to_string(),
+            min,
+            max,
+        }
+    }
+
+    /// what a best fit aims for: the exact amount, the middle of min and max, or the only bound
+    pub fn amount(&self) -> Option<f64> {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => Some((min + max) / 2.0),
+            (min, max) => min.or(max),
+        }
+    }
+}
+
+impl Diet {
+    /// unlike solving the square system in balance_diet, works with any number of foods,
+    /// and never returns negative amounts: if the targets need them, they're reported as infeasible
+    pub fn optimize(&self) -> Result<DietPlan, String> {
+        self.check_nutrient_counts()?;
+
+        // one constraint per bound, remembering how to describe it
+        let mut constraints = vec![];
+        let mut descriptions = vec![];
+        for (i, target) in self.targets.iter().enumerate() {
+            let amounts: Vec<f64> = self.foods.iter().map(|f| f.nutrients[i]).collect();
+            let bounds = match (target.min, target.max) {
+                (Some(min), Some(max)) if min == max => vec![(Relation::Equal, "=", min)],
+                (min, max) => min
+                    .map(|min| (Relation::GreaterOrEqual, ">=", min))
+                    .into_iter()
+                    .chain(max.map(|max| (Relation::LessOrEqual, "<=", max)))
+                    .collect(),
+            };
+            for (relation, symbol, bound) in bounds {
+                constraints.push(Constraint::new(amounts.clone(), relation, bound));
+                descriptions.push(format!("{} {} {}", target.name, symbol, bound));
+            }
+        }
+
+        let costs = self.foods.iter().map(|f| f.cost).collect();
+        match LinearProgram::new(costs, constraints).solve() {
+            LpSolution::Optimal { x, value } => Ok(DietPlan::Optimal {
+                totals: self.totals(&x),
+                quantities: x,
+                cost: value,
+            }),
+            LpSolution::Infeasible { conflicting } => Ok(DietPlan::Infeasible {
+                conflicts: conflicting
+                    .into_iter()
+                    .map(|i| descriptions[i].clone())
+                    .collect(),
+            }),
+            // only with negative costs, i.e. a food that pays us to eat it
+            LpSolution::Unbounded => {
+                Err("unbounded: the cost can decrease without limit".to_string())
+            }
+        }
+    }
+
+    /// least squares fit of the targets with non-negative amounts (NNLS), ignoring costs.
+    /// with more nutrients than foods there's usually no exact solution, and rref only tells us it's inconsistent
+    ///
+    /// `weights` (one per nutrient, >= 0) make some nutrients count more:
+    /// we minimize the sum of weight * residual^2
+    pub fn best_fit(&self, weights: Option<&[f64]>) -> Result<DietFit, String> {
+        let targets = self
+            .targets
+            .iter()
+            .map(|t| {
+                t.amount()
+                    .ok_or_else(|| format!("{} has no target amount", t.name))
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
+        let weights = match weights {
+            Some(weights) if weights.len() != targets.len() => {
+                return Err(format!(
+                    "{} weights, expected {} (one per nutrient)",
+                    weights.len(),
+                    targets.len()
+                ))
+            }
+            Some(weights) if weights.iter().any(|w| *w < 0.0) => {
+                return Err("weights can't be negative".to_string())
+            }
+            Some(weights) => weights.to_vec(),
+            None => vec![1.0; targets.len()],
+        };
+        self.check_nutrient_counts()?;
+
+        // weighting the rows by sqrt(weight) weights the squared residuals by weight
+        let a = DMatrix::from_fn(targets.len(), self.foods.len(), |r, c| {
+            weights[r].sqrt() * self.foods[c].nutrients[r]
+        });
+        let b = DVector::from_fn(targets.len(), |r, _| weights[r].sqrt() * targets[r]);
+        let quantities: Vec<f64> = nnls(&a, &b).iter().copied().collect();
+
+        let totals = self.totals(&quantities);
+        let residuals: Vec<f64> = totals
+            .iter()
+            .zip(&targets)
+            .map(|(t, target)| t - target)
+            .collect();
+        let deviations = residuals
+            .iter()
+            .zip(&targets)
+            .map(|(r, target)| (*target != 0.0).then(|| 100.0 * r / target))
+            .collect();
+        Ok(DietFit {
+            cost: self
+                .foods
+                .iter()
+                .zip(&quantities)
+                .map(|(f, q)| f.cost * q)
+                .sum(),
+            quantities,
+            totals,
+            residuals,
+            deviations,
+        })
+    }
+
+    fn check_nutrient_counts(&self) -> Result<(), String> {
+        for food in &self.foods {
+            if food.nutrients.len() != self.targets.len() {
+                return Err(format!(
+                    "{} has {} nutrients, expected {} (one per target)",
+                    food.name,
+                    food.nutrients.len(),
+                    self.targets.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// total of each nutrient for the given food quantities
+    pub fn totals(&self, quantities: &[f64]) -> Vec<f64> {
+        (0..self.targets.len())
+            .map(|i| {
+                self.foods
+                    .iter()
+                    .zip(quantities)
+                    .map(|(food, q)| food.nutrients[i] * q)
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    // milk, soy, whey from balance_diet, with (made up) costs
+    fn lay_foods() -> Vec<Food> {
+        vec![
+            Food::new("milk", 1.0, vec![36.0, 52.0, 0.0]),
+            Food::new("soy", 1.5, vec![51.0, 34.0, 7.0]),
+            Food::new("whey", 2.0, vec![13.0, 74.0, 1.1]),
+        ]
+    }
+
+    fn exact_targets(protein: f64, carbs: f64, fat: f64) -> Vec<NutrientTarget> {
+        vec![
+            NutrientTarget::exactly("protein", protein),
+            NutrientTarget::exactly("carbs", carbs),
+            NutrientTarget::exactly("fat", fat),
+        ]
+    }
+
+    #[test]
+    fn matches_square_system() {
+        let diet = Diet {
+            foods: lay_foods(),
+            targets: exact_targets(33.0, 45.0, 3.0),
+        };
+
+        let Ok(DietPlan::Optimal { quantities, .. }) = diet.optimize() else {
+            panic!("expected a plan");
+        };
+
+        // only one solution, so the same quantities as in balance_diet
+        assert_relative_eq!(quantities[0], 0.277, epsilon = 0.001);
+        assert_relative_eq!(quantities[1], 0.392, epsilon = 0.001);
+        assert_relative_eq!(quantities[2], 0.233, epsilon = 0.001);
+    }
+
+    #[test]
+    fn minimizes_cost_with_more_foods_than_nutrients() {
+        let mut foods = lay_foods();
+        foods.push(Food::new("oats", 0.5, vec![17.0, 66.0, 7.0]));
+        let diet = Diet {
+            foods,
+            targets: vec![
+                NutrientTarget::between("protein", Some(33.0), None),
+                NutrientTarget::between("carbs", Some(45.0), Some(80.0)),
+                NutrientTarget::between("fat", None, Some(5.0)),
+            ],
+        };
+
+        let Ok(DietPlan::Optimal {
+            quantities,
+            cost,
+            totals,
+        }) = diet.optimize()
+        else {
+            panic!("expected a plan");
+        };
+
+        assert!(quantities.iter().all(|q| *q >= 0.0));
+        assert!(totals[0] >= 33.0 - 1e-9);
+        assert!(totals[1] >= 45.0 - 1e-9 && totals[1] <= 80.0 + 1e-9);
+        assert!(totals[2] <= 5.0 + 1e-9);
+        // cheaper than the exact 33 / 45 / 3 mix, which also meets these bounds
+        let exact_cost = 0.277 * 1.0 + 0.392 * 1.5 + 0.233 * 2.0;
+        assert!(cost < exact_cost);
+    }
+
+    #[test]
+    fn fits_overdetermined_targets() {
+        // 2 foods, 3 nutrients: no exact solution
+        let diet = Diet {
+            foods: lay_foods()[..2].to_vec(),
+            targets: exact_targets(33.0, 45.0, 3.0),
+        };
+
+        let fit = diet.best_fit(None).unwrap();
+
+        assert!(fit.quantities.iter().all(|q| *q >= 0.0));
+        for i in 0..3 {
+            assert_relative_eq!(
+                fit.residuals[i],
+                fit.totals[i] - [33.0, 45.0, 3.0][i],
+                epsilon = 1e-9
+            );
+        }
+        assert_relative_eq!(
+            fit.deviations[0].unwrap(),
+            100.0 * fit.residuals[0] / 33.0,
+            epsilon = 1e-9
+        );
+
+        // weighting protein a lot makes it (almost) exact, at the cost of the others
+        let weighted = diet.best_fit(Some(&[100.0, 1.0, 1.0])).unwrap();
+        assert!(weighted.residuals[0].abs() < fit.residuals[0].abs());
+        assert!(weighted.residuals[0].abs() < 0.1);
+
+        assert!(diet.best_fit(Some(&[1.0])).is_err());
+    }
+
+    #[test]
+    fn fit_is_exact_when_possible() {
+        let diet = Diet {
+            foods: lay_foods(),
+            targets: exact_targets(33.0, 45.0, 3.0),
+        };
+
+        let fit = diet.best_fit(None).unwrap();
+
+        assert_relative_eq!(fit.quantities[0], 0.277, epsilon = 0.001);
+        assert!(fit.residuals.iter().all(|r| r.abs() < 1e-9));
+    }
+
+    #[test]
+    fn reports_conflicting_targets() {
+        // with 0 fat, only milk qualifies, which can't give 33 protein and 45 carbs at the same time:
+        // solving the square system would need negative amounts of soy or whey
+        let diet = Diet {
+            foods: lay_foods(),
+            targets: exact_targets(33.0, 45.0, 0.0),
+        };
+
+        let Ok(DietPlan::Infeasible { conflicts }) = diet.optimize() else {
+            panic!("expected conflicting targets");
+        };
+        assert_eq!(conflicts, vec!["protein = 33", "carbs = 45", "fat = 0"]);
+    }
+}
@@ -0,0 +1,297 @@
+use nalgebra::{DMatrix, DVector};
+use num_complex::Complex;
+
+/// below this, |λ| counts as 1 and an imaginary part as 0
+const EIGENVALUE_TOLERANCE: f64 = 1e-9;
+
+/// a discrete dynamical system x_{k+1} = A x_k, e.g. predator-prey, migration between regions,
+/// or the age classes of a population (see `leslie`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicalSystem {
+    pub a: DMatrix<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eigenpair {
+    pub value: Complex<f64>,
+    /// unit length, only for real eigenvalues
+    pub vector: Option<DVector<f64>>,
+}
+
+/// what happens to trajectories around the origin, from the magnitudes of the eigenvalues
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// all |λ| < 1: every trajectory goes to 0
+    Attractor,
+    /// all |λ| > 1: every trajectory (except 0) moves away
+    Repeller,
+    /// some |λ| < 1, others > 1: attracted along some directions, repelled along others
+    Saddle,
+    /// some |λ| = 1, the rest on one side: trajectories approach e.g. a steady state (like Markov chains)
+    Neutral,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LongTermBehavior {
+    pub origin: Origin,
+    /// the eigenvalue with the largest magnitude, which wins in the long run
+    pub dominant: Complex<f64>,
+    /// |dominant|: the factor the system eventually grows (or shrinks) by per step
+    pub growth_rate: f64,
+    /// the direction trajectories end up along (the dominant eigenvector),
+    /// None when the dominant eigenvalue is complex: then they rotate (spiral) instead
+    pub direction: Option<DVector<f64>>,
+}
+
+impl DynamicalSystem {
+    pub fn new(a: DMatrix<f64>) -> DynamicalSystem {
+        assert!(a.is_square(), "the matrix of a dynamical system is square");
+        DynamicalSystem { a }
+    }
+
+    /// Leslie model of a population in age classes: `fertility[i]` is the newborns per individual in class i,
+    /// `survival[i]` the fraction of class i that makes it to class i + 1
+    pub fn leslie(fertility: &[f64], survival: &[f64]) -> DynamicalSystem {
+        let n = fertility.len();
+        assert_eq!(
+            survival.len() + 1,
+            n,
+            "need a survival rate per age class but the last"
+        );
+        DynamicalSystem::new(DMatrix::from_fn(n, n, |r, c| match r {
+            0 => fertility[c],
+            _ if c + 1 == r => survival[c],
+            _ => 0.0,
+        }))
+    }
+
+    /// x0, x1 = A x0, ..., x_steps
+    pub fn trajectory(&self, x0: &DVector<f64>, steps: usize) -> Vec<DVector<f64>> {
+        assert_eq!(x0.len(), self.a.nrows(), "need one entry per variable");
+        let mut trajectory = vec![x0.clone()];
+        for _ in 0..steps {
+            let next = &self.a * trajectory.last().expect("starts with x0");
+            trajectory.push(next);
+        }
+        trajectory
+    }
+
+    /// eigenvalues with the largest magnitude first. x0 written in the eigenvectors, x0 = c1 v1 + ... + cn vn,
+    /// gives x_k = c1 λ1^k v1 + ... + cn λn^k vn, so these tell us where trajectories go
+    pub fn eigenpairs(&self) -> Vec<Eigenpair> {
+        let n = self.a.nrows();
+        let mut values: Vec<Complex<f64>> = self.a.complex_eigenvalues().iter().copied().collect();
+        values.sort_by(|a, b| b.norm().total_cmp(&a.norm()));
+        values
+            .into_iter()
+            .map(|value| {
+                let vector = (value.im.abs() < EIGENVALUE_TOLERANCE)
+                    .then(|| {
+                        let a_minus_lambda = DMatrix::from_fn(n, n, |r, c| match r == c {
+                            true => self.a[(r, c)] - value.re,
+                            false => self.a[(r, c)],
+                        });
+                        least_stretched_direction(a_minus_lambda)
+                    })
+                    .map(|v| {
+                        // the same direction regardless of the null space basis: largest entry positive
+                        let largest = v.iter().copied().max_by(|a, b| a.abs().total_cmp(&b.abs()));
+                        v.normalize() * largest.unwrap_or(1.0).signum()
+                    });
+                Eigenpair {
+                    value: Complex::new(
+                        value.re,
+                        if value.im.abs() < EIGENVALUE_TOLERANCE {
+                            0.0
+                        } else {
+                            value.im
+                        },
+                    ),
+                    vector,
+                }
+            })
+            .collect()
+    }
+
+    pub fn long_term_behavior(&self) -> LongTermBehavior {
+        let eigenpairs = self.eigenpairs();
+        let magnitudes: Vec<f64> = eigenpairs.iter().map(|e| e.value.norm()).collect();
+        let shrinking = magnitudes.iter().any(|m| *m < 1.0 - EIGENVALUE_TOLERANCE);
+        let growing = magnitudes.iter().any(|m| *m > 1.0 + EIGENVALUE_TOLERANCE);
+        let neutral = magnitudes
+            .iter()
+            .any(|m| (m - 1.0).abs() <= EIGENVALUE_TOLERANCE);
+        let origin = match (shrinking, growing, neutral) {
+            (true, true, _) => Origin::Saddle,
+            (_, _, true) => Origin::Neutral,
+            (true, false, false) => Origin::Attractor,
+            _ => Origin::Repeller,
+        };
+        let dominant = eigenpairs.first().expect("a non-empty matrix");
+        LongTermBehavior {
+            origin,
+            dominant: dominant.value,
+            growth_rate: dominant.value.norm(),
+            direction: dominant.vector.clone(),
+        }
+    }
+}
+
+/// the unit vector x minimizing |Mx|: the right singular vector of the smallest singular value.
+/// for M = A - λI that's the eigenvector. λ is only approximate, so M is usually not exactly singular
+/// and a null space (rref with an absolute tolerance) would often be empty
+fn least_stretched_direction(m: DMatrix<f64>) -> DVector<f64> {
+    let svd = m.svd(false, true);
+    let smallest = svd
+        .singular_values
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+        .expect("a non-empty matrix");
+    svd.v_t.expect("computed v_t").row(smallest).transpose()
+}
+
+impl LongTermBehavior {
+    /// e.g. "saddle: grows by 1.02 per step, along (0.61, 0.79)"
+    pub fn describe(&self) -> String {
+        let origin = match self.origin {
+            Origin::Attractor => "attractor",
+            Origin::Repeller => "repeller",
+            Origin::Saddle => "saddle",
+            Origin::Neutral => "neutral",
+        };
+        let change = match self.growth_rate >= 1.0 {
+            true => "grows",
+            false => "shrinks",
+        };
+        let movement = match &self.direction {
+            Some(v) => format!(
+                "along ({})",
+                v.iter()
+                    .map(|x| format!("{:.2}", x))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            None => format!(
+                "rotating by {:.1}° per step",
+                self.dominant.arg().to_degrees().abs()
+            ),
+        };
+        format!(
+            "{}: {} by {:.3} per step, {}",
+            origin, change, self.growth_rate, movement
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn system(values: [f64; 4]) -> DynamicalSystem {
+        DynamicalSystem::new(DMatrix::from_row_slice(2, 2, &values))
+    }
+
+    // predator-prey (owls and rats) from Lay's linear algebra (section 5.6), with predation parameter .104
+    #[test]
+    fn predator_prey_grows_along_dominant_eigenvector() {
+        let system = system([0.5, 0.4, -0.104, 1.1]);
+
+        let eigenpairs = system.eigenpairs();
+        assert_relative_eq!(eigenpairs[0].value.re, 1.02, epsilon = 1e-9);
+        assert_relative_eq!(eigenpairs[1].value.re, 0.58, epsilon = 1e-9);
+
+        let behavior = system.long_term_behavior();
+        assert_eq!(behavior.origin, Origin::Saddle);
+        // Lay: v1 = (10, 13), 10 owls per 13 (thousand) rats
+        let direction = behavior.direction.clone().unwrap();
+        assert_relative_eq!(direction[0] / direction[1], 10.0 / 13.0, epsilon = 1e-9);
+
+        // eventually x_{k+1} ≈ 1.02 x_k
+        let trajectory = system.trajectory(&DVector::from_row_slice(&[1.0, 1.0]), 100);
+        assert_eq!(trajectory.len(), 101);
+        assert_relative_eq!(trajectory[100][1] / trajectory[99][1], 1.02, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn classifies_origin() {
+        assert_eq!(
+            system([0.8, 0.0, 0.0, 0.64]).long_term_behavior().origin,
+            Origin::Attractor
+        );
+        assert_eq!(
+            system([1.44, 0.0, 0.0, 1.2]).long_term_behavior().origin,
+            Origin::Repeller
+        );
+        let saddle = system([1.25, -0.75, -0.75, 1.25]).long_term_behavior();
+        assert_eq!(saddle.origin, Origin::Saddle);
+        assert_relative_eq!(saddle.growth_rate, 2.0, epsilon = 1e-9);
+        let direction = saddle.direction.unwrap();
+        assert_relative_eq!(direction[0], -direction[1], epsilon = 1e-9);
+        // a Markov chain
+        assert_eq!(
+            system([0.9, 0.2, 0.1, 0.8]).long_term_behavior().origin,
+            Origin::Neutral
+        );
+    }
+
+    #[test]
+    fn finds_eigenvectors_of_inexact_eigenvalues() {
+        // λ ≈ 148.1, 60.57, -17.67. with entries this large, the computed λ leave A - λI too far from singular
+        // for rref's absolute tolerance (there was no eigenvector for 60.57)
+        #[rustfmt::skip]
+        let system = DynamicalSystem::new(DMatrix::from_row_slice(3, 3, &[
+            96.0, 22.0, 54.0,
+            4.0, 63.0, 65.0,
+            61.0, 38.0, 32.0,
+        ]));
+
+        let eigenpairs = system.eigenpairs();
+
+        assert_eq!(eigenpairs.len(), 3);
+        for eigenpair in eigenpairs {
+            assert_eq!(eigenpair.value.im, 0.0);
+            let v = eigenpair.vector.unwrap();
+            assert_relative_eq!(v.norm(), 1.0, epsilon = 1e-12);
+            // Av = λv
+            assert_relative_eq!(&system.a * &v, v * eigenpair.value.re, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn complex_eigenvalues_spiral() {
+        // eigenvalues .9 ± .2i, |λ| ≈ .92: spirals in
+        let behavior = system([0.8, 0.5, -0.1, 1.0]).long_term_behavior();
+
+        assert_eq!(behavior.origin, Origin::Attractor);
+        assert_relative_eq!(behavior.growth_rate, 0.85f64.sqrt(), epsilon = 1e-9);
+        assert_relative_eq!(behavior.dominant.im.abs(), 0.2, epsilon = 1e-9);
+        assert_eq!(behavior.direction, None);
+        assert!(behavior.describe().contains("rotating"));
+    }
+
+    #[test]
+    fn leslie_model_settles_into_age_distribution() {
+        let system = DynamicalSystem::leslie(&[0.0, 1.5, 1.0], &[0.5, 0.8]);
+
+        #[rustfmt::skip]
+        assert_eq!(system.a, DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.5, 1.0,
+            0.5, 0.0, 0.0,
+            0.0, 0.8, 0.0,
+        ]));
+        let behavior = system.long_term_behavior();
+        let trajectory = system.trajectory(&DVector::from_row_slice(&[100.0, 0.0, 0.0]), 200);
+        // the age distribution approaches the dominant eigenvector, the total grows by λ1 per step
+        let last = &trajectory[200];
+        let direction = behavior.direction.unwrap();
+        assert_relative_eq!(last.normalize(), direction, epsilon = 1e-6);
+        assert_relative_eq!(
+            last.sum() / trajectory[199].sum(),
+            behavior.growth_rate,
+            epsilon = 1e-6
+        );
+    }
+}
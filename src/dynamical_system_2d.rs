@@ -0,0 +1,91 @@
+use crate::functions::{draw_line2d_general_form, draw_trajectory_2d};
+use crate::gui::{show_gui_message, spawn_gui, GuiMessage};
+use crate::system_2d::WorldView;
+use bevy::prelude::*;
+use linear_alg::dynamical_system::DynamicalSystem;
+use nalgebra::{DMatrix, DVector};
+
+/// plots trajectories of a 2d dynamical system x_{k+1} = A x_k from a few starting points,
+/// together with the lines through the eigenvectors (if the eigenvalues are real).
+/// the panel says what happens in the long term
+pub fn add_dynamical_system_2d_system(app: &mut App) {
+    app.add_systems(Startup, setup_trajectories)
+        .add_systems(Update, (show_long_term_behavior, draw_trajectories));
+}
+
+#[derive(Resource)]
+struct Trajectories {
+    paths: Vec<Vec<Vec2>>,
+    eigenvectors: Vec<Vec2>,
+    long_term_behavior: String,
+}
+
+fn setup_trajectories(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_gui(&mut commands, &asset_server, "Long term behavior:");
+
+    // saddle from Lay's linear algebra (section 5.6): eigenvalues 2 along (1, -1) and .5 along (1, 1)
+    let system = DynamicalSystem::new(DMatrix::from_row_slice(2, 2, &[1.25, -0.75, -0.75, 1.25]));
+
+    // starting close to the attracting direction, so we see them come in before they're pushed out
+    let starts = [
+        (3.0, 2.9),
+        (3.0, 3.1),
+        (-3.0, -2.9),
+        (-3.0, -3.1),
+        (2.9, 3.0),
+        (-2.9, -3.0),
+    ];
+    let paths = starts
+        .iter()
+        .map(|(x, y)| {
+            system
+                .trajectory(&DVector::from_row_slice(&[*x, *y]), 7)
+                .iter()
+                .map(|p| Vec2::new(p[0] as f32, p[1] as f32))
+                .collect()
+        })
+        .collect();
+    let eigenvectors = system
+        .eigenpairs()
+        .iter()
+        .filter_map(|e| e.vector.as_ref())
+        .map(|v| Vec2::new(v[0] as f32, v[1] as f32))
+        .collect();
+
+    commands.insert_resource(Trajectories {
+        paths,
+        eigenvectors,
+        long_term_behavior: system.long_term_behavior().describe(),
+    });
+}
+
+fn show_long_term_behavior(
+    trajectories: Res<Trajectories>,
+    mut message: Query<&mut Text, With<GuiMessage>>,
+) {
+    if !trajectories.is_changed() {
+        return;
+    }
+    let Ok(mut message) = message.get_single_mut() else {
+        return;
+    };
+    show_gui_message(&mut message, &trajectories.long_term_behavior, false);
+}
+
+fn draw_trajectories(mut gizmos: Gizmos, trajectories: Res<Trajectories>, view: Res<WorldView>) {
+    for v in &trajectories.eigenvectors {
+        // the line through the origin along v: its normal is v rotated by 90°
+        let normal = v.perp();
+        draw_line2d_general_form(
+            &mut gizmos,
+            normal.x,
+            normal.y,
+            0.0,
+            view.reach(),
+            Color::BLUE,
+        );
+    }
+    for path in &trajectories.paths {
+        draw_trajectory_2d(&mut gizmos, path, view.pixels(3.0), Color::YELLOW);
+    }
+}
@@ -1,45 +1,574 @@
+use crate::field::{parse_decimal, Field};
+use crate::linear_system::{LinearSystem, Solution};
+use nalgebra::{DMatrix, DVector};
+use num_complex::Complex;
+use num_rational::BigRational;
+use num_traits::{One, Pow, Zero};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+/// a circuit, e.g. from a SPICE-like netlist. nodes are identified by their index in `nodes`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Circuit {
+    pub nodes: Vec<String>,
+    pub elements: Vec<Element>,
+}
+
+/// a branch of the circuit, oriented from `from` to `to`:
+/// its current is positive when flowing from `from` to `to`, and its voltage drop is V(from) - V(to)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub kind: ElementKind,
+    pub from: usize,
+    pub to: usize,
+    /// ohm, volt, ampere, farad or henry, depending on the kind
+    pub value: BigRational,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementKind {
+    Resistor,
+    /// `from` is the + terminal: V(from) - V(to) = value
+    VoltageSource,
+    /// `value` flows from `from` through the source to `to` (like SPICE's `I1 n+ n- value`)
+    CurrentSource,
+    /// farad, only for ac analysis
+    Capacitor,
+    /// henry, only for ac analysis
+    Inductor,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshSolution {
+    /// one row per independent loop, one column per element: 1 / -1 if the loop goes along / against it, else 0
+    pub loop_matrix: DMatrix<i64>,
+    /// circulating current of each loop, in the loop's direction
+    pub loop_currents: Vec<BigRational>,
+    /// current of each element, in the element's direction
+    pub branch_currents: Vec<BigRational>,
+    /// V(from) - V(to) of each element
+    pub voltage_drops: Vec<BigRational>,
+}
+
+/// exact (`BigRational`) for dc, `Complex<f64>` phasors for ac
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodalSolution<T: Field> {
+    /// voltage of each node relative to ground (which is 0)
+    pub node_voltages: Vec<T>,
+    /// current of each element, in the element's direction
+    pub branch_currents: Vec<T>,
+    /// V(from) - V(to) of each element
+    pub voltage_drops: Vec<T>,
+}
+
+/// one row of a Bode plot
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodePoint {
+    /// Hz
+    pub frequency: f64,
+    /// V(output) / V(input)
+    pub gain: Complex<f64>,
+    /// 20 log10 |gain|
+    pub magnitude_db: f64,
+    pub phase_degrees: f64,
+}
+
+/// magnitude and phase (in degrees) of a phasor
+pub fn polar(value: &Complex<f64>) -> (f64, f64) {
+    (value.norm(), value.arg().to_degrees())
+}
+
+/// `per_decade` frequencies per factor of 10, from `start` to `stop` (both included), e.g. for a sweep
+pub fn log_frequencies(start: f64, stop: f64, per_decade: usize) -> Result<Vec<f64>, String> {
+    if start <= 0.0 {
+        return Err(format!("start frequency {} isn't positive", start));
+    }
+    if stop < start {
+        return Err(format!(
+            "stop frequency {} is below the start frequency {}",
+            stop, start
+        ));
+    }
+    if per_decade == 0 {
+        return Err("need at least 1 frequency per decade".to_string());
+    }
+    let steps = ((stop / start).log10() * per_decade as f64).round() as usize;
+    Ok((0..=steps)
+        .map(|i| start * 10f64.powf(i as f64 / per_decade as f64))
+        .collect())
+}
+
+/// frequency, magnitude and phase as aligned columns
+pub fn bode_table(points: &[BodePoint]) -> String {
+    let mut table = format!(
+        "{:>14} {:>14} {:>14}\n",
+        "frequency (Hz)", "magnitude (dB)", "phase (deg)"
+    );
+    for p in points {
+        table += &format!(
+            "{:>14.3} {:>14.3} {:>14.3}\n",
+            p.frequency, p.magnitude_db, p.phase_degrees
+        );
+    }
+    table
+}
+
+/// names of the reference node, with voltage 0
+const GROUND_NAMES: [&str; 2] = ["0", "gnd"];
+
+/// parses lines like `R1 n1 n2 4` (resistor, ohm), `V1 n+ n- 30` (voltage source, volt), `I1 n+ n- 2` (current source, ampere),
+/// `C1 n1 n2 1u` (capacitor, farad) and `L1 n1 n2 10m` (inductor, henry)
+/// values can have SPICE suffixes (`4.7k`, `10m`). lines starting with '*' are comments, with '.' directives (ignored)
+pub fn parse_netlist(str: &str) -> Result<Circuit, String> {
+    let mut circuit = Circuit {
+        nodes: vec![],
+        elements: vec![],
+    };
+    for (index, line) in str.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('*') || line.starts_with('.') {
+            continue;
+        }
+        let error = |message: String| format!("line {}: {}", index + 1, message);
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [name, from, to, value] = fields[..] else {
+            return Err(error(format!(
+                "expected 'name node node value', got: {}",
+                line
+            )));
+        };
+        let kind = match name.chars().next().map(|c| c.to_ascii_uppercase()) {
+            Some('R') => ElementKind::Resistor,
+            Some('V') => ElementKind::VoltageSource,
+            Some('I') => ElementKind::CurrentSource,
+            Some('C') => ElementKind::Capacitor,
+            Some('L') => ElementKind::Inductor,
+            _ => return Err(error(format!("unsupported element '{}'", name))),
+        };
+        let element = Element {
+            name: name.to_string(),
+            kind,
+            from: circuit.node(from),
+            to: circuit.node(to),
+            value: parse_value(value).map_err(error)?,
+        };
+        circuit.elements.push(element);
+    }
+    Ok(circuit)
+}
+
+/// an exact decimal with an optional SPICE suffix, e.g. "4.7k" = 4700, "10m" = 1/100
+pub fn parse_value(str: &str) -> Result<BigRational, String> {
+    let lower = str.to_ascii_lowercase();
+    // "meg" before "m" (milli)
+    let (number, exponent) = [
+        ("meg", 6),
+        ("f", -15),
+        ("p", -12),
+        ("n", -9),
+        ("u", -6),
+        ("m", -3),
+        ("k", 3),
+        ("g", 9),
+        ("t", 12),
+    ]
+    .iter()
+    .find_map(|(suffix, exponent)| lower.strip_suffix(suffix).map(|n| (n, *exponent)))
+    .unwrap_or((&lower, 0));
+
+    let number = parse_decimal(number).map_err(|_| format!("invalid value '{}'", str))?;
+    Ok(number * Pow::pow(BigRational::from_i64(10), exponent))
+}
+
+impl Circuit {
+    /// index of the node with this name, added if new
+    fn node(&mut self, name: &str) -> usize {
+        match self.nodes.iter().position(|n| n == name) {
+            Some(i) => i,
+            None => {
+                self.nodes.push(name.to_string());
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// a set of independent loops, as a loop matrix (see `MeshSolution`)
+    ///
+    /// we take a spanning tree of the circuit graph: each element outside of it closes exactly one loop with the tree,
+    /// and these loops are independent since each has an element no other loop has.
+    /// that's elements - nodes + connected parts loops, the number of unknowns we need
+    pub fn independent_loops(&self) -> DMatrix<i64> {
+        let nodes = self.nodes.len();
+        // the tree element leading to each node from its parent, and the parent. None for roots
+        let mut parent: Vec<Option<(usize, usize)>> = vec![None; nodes];
+        let mut depth = vec![0; nodes];
+        let mut visited = vec![false; nodes];
+        let mut in_tree = vec![false; self.elements.len()];
+
+        // breadth first from each node not reached yet (once per connected part)
+        for root in 0..nodes {
+            if visited[root] {
+                continue;
+            }
+            visited[root] = true;
+            let mut queue = VecDeque::from([root]);
+            while let Some(node) = queue.pop_front() {
+                for (i, e) in self.elements.iter().enumerate() {
+                    let next = match (e.from == node, e.to == node) {
+                        (true, false) => e.to,
+                        (false, true) => e.from,
+                        _ => continue,
+                    };
+                    if !visited[next] {
+                        visited[next] = true;
+                        in_tree[i] = true;
+                        parent[next] = Some((node, i));
+                        depth[next] = depth[node] + 1;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        let loops: Vec<usize> = (0..self.elements.len()).filter(|&i| !in_tree[i]).collect();
+        let mut matrix = DMatrix::zeros(loops.len(), self.elements.len());
+        for (l, &closing) in loops.iter().enumerate() {
+            // along the closing element from -> to, then back through the tree from `to` to `from`
+            matrix[(l, closing)] = 1;
+            let (mut a, mut b) = (self.elements[closing].to, self.elements[closing].from);
+            // walk a up (leaving the loop's end) and b up (arriving at the loop's start, so reversed) until they meet
+            while a != b {
+                if depth[a] >= depth[b] {
+                    let (up, e) = parent[a].expect("not a root, since deeper");
+                    matrix[(l, e)] += self.direction(e, a, up);
+                    a = up;
+                } else {
+                    let (up, e) = parent[b].expect("not a root, since deeper");
+                    matrix[(l, e)] += self.direction(e, up, b);
+                    b = up;
+                }
+            }
+        }
+        matrix
+    }
+
+    /// 1 if element `e` goes from `a` to `b`, else -1
+    fn direction(&self, e: usize, a: usize, b: usize) -> i64 {
+        let element = &self.elements[e];
+        if element.from == a && element.to == b {
+            1
+        } else {
+            -1
+        }
+    }
+
+    /// loop (mesh) current analysis, exact.
+    /// Kirchhoff's voltage law around each loop: sum of the drops (R * I for resistors, the source voltage for sources) is 0,
+    /// with the element currents being the sums of the loop currents through them: I = B^T i for the loop matrix B.
+    /// so (B R B^T) i = -B E, with R the resistances (diagonal) and E the source voltages
+    ///
+    /// a current source would fix a loop current instead of adding a voltage, use `nodal_analysis` for those
+    pub fn mesh_analysis(&self) -> Result<MeshSolution, String> {
+        for e in &self.elements {
+            match e.kind {
+                ElementKind::Resistor | ElementKind::VoltageSource => {}
+                ElementKind::CurrentSource => {
+                    return Err(format!(
+                        "mesh analysis doesn't support current sources ({}), use nodal analysis",
+                        e.name
+                    ))
+                }
+                _ => return Err(format!("{} needs ac analysis", e.name)),
+            }
+        }
+        let loop_matrix = self.independent_loops();
+        let b = loop_matrix.map(BigRational::from_i64);
+        let resistances =
+            DMatrix::from_fn(self.elements.len(), self.elements.len(), |r, c| {
+                match (r == c, self.elements[r].kind) {
+                    (true, ElementKind::Resistor) => self.elements[r].value.clone(),
+                    _ => BigRational::zero(),
+                }
+            });
+        let sources = DVector::from_fn(self.elements.len(), |r, _| match self.elements[r].kind {
+            ElementKind::VoltageSource => self.elements[r].value.clone(),
+            _ => BigRational::zero(),
+        });
+
+        let coefficients = &b * resistances * b.transpose();
+        let constants = -(&b * &sources);
+        let loop_currents = match LinearSystem::new(coefficients, constants).solve() {
+            Solution::Unique(currents) => currents,
+            // e.g. a loop of only voltage sources
+            Solution::Inconsistent => {
+                return Err("no solution: the voltage sources contradict each other".to_string())
+            }
+            Solution::Infinite { .. } => {
+                return Err("currents not determined (a loop without resistance?)".to_string())
+            }
+        };
+
+        let branch_currents = b.transpose() * &loop_currents;
+        let voltage_drops: Vec<BigRational> = self
+            .elements
+            .iter()
+            .zip(branch_currents.iter())
+            .map(|(e, current)| match e.kind {
+                ElementKind::Resistor => e.value.clone() * current.clone(),
+                _ => e.value.clone(),
+            })
+            .collect();
+
+        Ok(MeshSolution {
+            loop_matrix,
+            loop_currents: loop_currents.iter().cloned().collect(),
+            branch_currents: branch_currents.iter().cloned().collect(),
+            voltage_drops,
+        })
+    }
+
+    /// modified nodal analysis (MNA), exact.
+    /// unknowns: the voltage of each node except ground, and the current through each voltage source.
+    /// Kirchhoff's current law at each node (what flows out through resistors and voltage sources = what the current sources push in),
+    /// plus one equation V(+) - V(-) = value per voltage source
+    ///
+    /// this is the dc solution, capacitors and inductors need `ac_analysis`
+    pub fn nodal_analysis(&self) -> Result<NodalSolution<BigRational>, String> {
+        self.modified_nodal(
+            |e| match e.kind {
+                ElementKind::Resistor if e.value.is_zero() => Err(format!(
+                    "{} has 0 ohm, use a 0 V source for a short",
+                    e.name
+                )),
+                ElementKind::Resistor => Ok(BigRational::one() / e.value.clone()),
+                _ => Err(format!("{} needs ac analysis", e.name)),
+            },
+            |e| e.value.clone(),
+        )
+    }
+
+    /// steady state with sinusoidal sources of `frequency` (Hz), using complex impedances:
+    /// Z = R, 1 / (jωC), jωL with ω = 2π frequency. the sources' values are amplitudes, all with phase 0
+    pub fn ac_analysis(&self, frequency: f64) -> Result<NodalSolution<Complex<f64>>, String> {
+        let omega = 2.0 * PI * frequency;
+        self.modified_nodal(
+            |e| {
+                let value = e.value.to_f64();
+                let impedance = match e.kind {
+                    ElementKind::Resistor => Complex::new(value, 0.0),
+                    // admittance jωC, which is fine to be 0 (at 0 Hz)
+                    ElementKind::Capacitor => return Ok(Complex::new(0.0, omega * value)),
+                    ElementKind::Inductor => Complex::new(0.0, omega * value),
+                    _ => unreachable!("only called for passive elements"),
+                };
+                if impedance.is_negligible() {
+                    return Err(format!("{} has 0 impedance at {} Hz", e.name, frequency));
+                }
+                Ok(impedance.inv())
+            },
+            |e| Complex::new(e.value.to_f64(), 0.0),
+        )
+    }
+
+    /// gain V(output) / (value of the voltage source `input`) at each frequency, e.g. for a filter
+    pub fn frequency_sweep(
+        &self,
+        input: &str,
+        output: &str,
+        frequencies: &[f64],
+    ) -> Result<Vec<BodePoint>, String> {
+        let source = self
+            .element_index(input)
+            .filter(|&i| self.elements[i].kind == ElementKind::VoltageSource)
+            .ok_or_else(|| format!("no voltage source '{}'", input))?;
+        let output = self
+            .nodes
+            .iter()
+            .position(|n| n == output)
+            .ok_or_else(|| format!("no node '{}'", output))?;
+        let amplitude = self.elements[source].value.to_f64();
+        if amplitude == 0.0 {
+            return Err(format!("source '{}' has zero amplitude", input));
+        }
+
+        frequencies
+            .iter()
+            .map(|&frequency| {
+                let solution = self.ac_analysis(frequency)?;
+                let gain = solution.node_voltages[output] / amplitude;
+                let (magnitude, phase_degrees) = polar(&gain);
+                Ok(BodePoint {
+                    frequency,
+                    gain,
+                    magnitude_db: 20.0 * magnitude.log10(),
+                    phase_degrees,
+                })
+            })
+            .collect()
+    }
+
+    /// MNA over any field, given the admittance (1 / impedance) of the passive elements and the value of the sources
+    fn modified_nodal<T, A, S>(&self, admittance: A, source: S) -> Result<NodalSolution<T>, String>
+    where
+        T: Field,
+        A: Fn(&Element) -> Result<T, String>,
+        S: Fn(&Element) -> T,
+    {
+        let ground = self
+            .nodes
+            .iter()
+            .position(|n| GROUND_NAMES.iter().any(|g| n.eq_ignore_ascii_case(g)))
+            .ok_or_else(|| format!("no ground node (one of {:?})", GROUND_NAMES))?;
+        // row / column of each node's voltage, None for ground
+        let mut unknowns = 0;
+        let node_index: Vec<Option<usize>> = (0..self.nodes.len())
+            .map(|n| {
+                (n != ground).then(|| {
+                    unknowns += 1;
+                    unknowns - 1
+                })
+            })
+            .collect();
+        let sources: Vec<usize> = (0..self.elements.len())
+            .filter(|&i| self.elements[i].kind == ElementKind::VoltageSource)
+            .collect();
+        let size = unknowns + sources.len();
+
+        let mut a = DMatrix::from_element(size, size, T::zero());
+        let mut b = DVector::from_element(size, T::zero());
+        let mut admittances = vec![None; self.elements.len()];
+        for (i, e) in self.elements.iter().enumerate() {
+            let (from, to) = (node_index[e.from], node_index[e.to]);
+            match e.kind {
+                // leaves `from`, arrives at `to`: known, so to the right hand side
+                ElementKind::CurrentSource => {
+                    if let Some(from) = from {
+                        b[from] = b[from].clone() - source(e);
+                    }
+                    if let Some(to) = to {
+                        b[to] = b[to].clone() + source(e);
+                    }
+                }
+                ElementKind::VoltageSource => {}
+                // y (V(from) - V(to)) leaves `from` and arrives at `to`
+                _ => {
+                    let y = admittance(e)?;
+                    if let Some(from) = from {
+                        a[(from, from)] = a[(from, from)].clone() + y.clone();
+                    }
+                    if let Some(to) = to {
+                        a[(to, to)] = a[(to, to)].clone() + y.clone();
+                    }
+                    if let (Some(from), Some(to)) = (from, to) {
+                        a[(from, to)] = a[(from, to)].clone() - y.clone();
+                        a[(to, from)] = a[(to, from)].clone() - y.clone();
+                    }
+                    admittances[i] = Some(y);
+                }
+            }
+        }
+        for (k, &i) in sources.iter().enumerate() {
+            let e = &self.elements[i];
+            let column = unknowns + k;
+            // the source's current leaves `from` and arrives at `to` too
+            if let Some(from) = node_index[e.from] {
+                a[(from, column)] = T::one();
+                a[(column, from)] = T::one();
+            }
+            if let Some(to) = node_index[e.to] {
+                a[(to, column)] = -T::one();
+                a[(column, to)] = -T::one();
+            }
+            b[column] = source(e);
+        }
+
+        let x = match LinearSystem::new(a, b).solve() {
+            Solution::Unique(x) => x,
+            Solution::Inconsistent => {
+                return Err("no solution: the sources contradict each other".to_string())
+            }
+            Solution::Infinite { .. } => {
+                return Err("voltages not determined (a part not connected to ground?)".to_string())
+            }
+        };
+
+        let node_voltages: Vec<T> = node_index
+            .iter()
+            .map(|i| i.map_or_else(T::zero, |i| x[i].clone()))
+            .collect();
+        let voltage_drops: Vec<T> = self
+            .elements
+            .iter()
+            .map(|e| node_voltages[e.from].clone() - node_voltages[e.to].clone())
+            .collect();
+        let branch_currents = self
+            .elements
+            .iter()
+            .zip(&voltage_drops)
+            .enumerate()
+            .map(|(i, (e, drop))| match (e.kind, &admittances[i]) {
+                (ElementKind::CurrentSource, _) => source(e),
+                (ElementKind::VoltageSource, _) => {
+                    let k = sources
+                        .iter()
+                        .position(|&s| s == i)
+                        .expect("a voltage source");
+                    x[unknowns + k].clone()
+                }
+                (_, Some(y)) => y.clone() * drop.clone(),
+                (_, None) => unreachable!("admittance set for all passive elements"),
+            })
+            .collect();
+
+        Ok(NodalSolution {
+            node_voltages,
+            branch_currents,
+            voltage_drops,
+        })
+    }
+
+    pub fn element_index(&self, name: &str) -> Option<usize> {
+        self.elements.iter().position(|e| e.name == name)
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use super::*;
+    use crate::dense_matrix::{for_each_backend, DenseMatrix};
+    use crate::field::rational;
+    use crate::row_reduction::rref;
     use approx::assert_relative_eq;
-    use peroxide::fuga::{matrix, LinearAlgebra, Shape::Col};
 
-    fn as_resistance_vec(l: Loop) -> Vec<f64> {
-        vec![l.resistance_l1, l.resistance_l2, l.resistance_l3]
+    fn as_resistance_vec(l: Loop) -> Vec<BigRational> {
+        vec![
+            rational(l.resistance_l1, 1),
+            rational(l.resistance_l2, 1),
+            rational(l.resistance_l3, 1),
+        ]
     }
 
     // a loop with its resistance coefficients, coming from loop 1, loop 2, loop 3
     #[derive(Debug)]
     struct Loop {
-        resistance_l1: f64,
-        resistance_l2: f64,
-        resistance_l3: f64,
-        voltage: f64,
+        resistance_l1: i64,
+        resistance_l2: i64,
+        resistance_l3: i64,
+        voltage: i64,
     }
 
     // based on an example from Lay's linear algebra
     #[test]
     fn find_loop_currents() {
-        let l1 = Loop {
-            resistance_l1: 11.0,
-            resistance_l2: -3.0,
-            resistance_l3: 0.0,
-            voltage: 30.0,
-        };
-        let l2 = Loop {
-            resistance_l1: -3.0,
-            resistance_l2: 6.0,
-            resistance_l3: -1.0,
-            voltage: 5.0,
-        };
-        let l3 = Loop {
-            resistance_l1: 0.0,
-            resistance_l2: -1.0,
-            resistance_l3: 3.0,
-            // -5 from loop 2 and -20 from loop 3 (sign due to direction)
-            voltage: -25.0,
-        };
+        let [l1, l2, l3] = loops();
 
-        let loop_voltages = vec![l1.voltage, l2.voltage, l3.voltage];
+        let loop_voltages = vec![
+            rational(l1.voltage, 1),
+            rational(l2.voltage, 1),
+            rational(l3.voltage, 1),
+        ];
 
         // finding the currents for 3 loops in a circuit, based on V = IR respectively
         // we build one such equation per loop and put it in a system, to calculate the currents
@@ -52,20 +581,332 @@ mod test {
         ]
         .concat()
         .to_owned();
-        let a = matrix(all_cols, 3, 4, Col);
-        // println!("a: {:?}", a);
+        let a = DMatrix::from_column_slice(3, 4, &all_cols);
+        // println!("a: {}", a);
+
+        let rref_a = rref(&a);
+
+        let c4 = rref_a.matrix.column(3);
+
+        assert_eq!(c4[0], rational(3, 1));
+        assert_eq!(c4[1], rational(1, 1));
+        assert_eq!(c4[2], rational(-8, 1));
+    }
+
+    // same circuit, solved directly (no rref) with each backend
+    #[test]
+    fn find_loop_currents_all_backends() {
+        for (backend, currents) in for_each_backend!(loop_currents) {
+            let currents = currents.unwrap_or_else(|| panic!("{} found no currents", backend));
+            assert_relative_eq!(currents[0], 3.0, epsilon = 1e-10);
+            assert_relative_eq!(currents[1], 1.0, epsilon = 1e-10);
+            assert_relative_eq!(currents[2], -8.0, epsilon = 1e-10);
+        }
+    }
+
+    fn loop_currents<M: DenseMatrix>() -> Option<Vec<f64>> {
+        let loops = loops();
+        let resistances = M::from_fn(3, 3, |r, c| {
+            let l = &loops[r];
+            [l.resistance_l1, l.resistance_l2, l.resistance_l3][c] as f64
+        });
+        let voltages: Vec<f64> = loops.iter().map(|l| l.voltage as f64).collect();
+        resistances.solve(&voltages)
+    }
+
+    // the circuit of find_loop_currents as a netlist: 3 meshes side by side,
+    // t0..t3 along the top, b0..b3 along the bottom, m between R6 and V2 on the vertical shared by loops 2 and 3
+    const LAY_NETLIST: &str = "
+* loop 1
+V1 t0 b0 30
+R1 t0 t1 4
+R2 b1 b0 4
+* shared by loops 1 and 2
+R3 t1 b1 3
+* loop 2
+R4 t1 t2 1
+R5 b2 b1 1
+* shared by loops 2 and 3
+R6 t2 m 1
+V2 b2 m 5
+* loop 3
+R7 t2 t3 1
+R8 b3 b2 1
+V3 t3 b3 20
+.end
+";
+
+    #[test]
+    fn mesh_analysis_from_netlist() {
+        let circuit = parse_netlist(LAY_NETLIST).unwrap();
+        assert_eq!(circuit.nodes.len(), 9);
+
+        let solution = circuit.mesh_analysis().unwrap();
+
+        // elements - nodes + 1 independent loops, found automatically
+        assert_eq!(solution.loop_matrix.nrows(), 11 - 9 + 1);
+        // the loops the search picks aren't necessarily the meshes of the drawing, so their currents can differ from 3, 1, -8.
+        // the element currents are the same though: loop 1's outer elements carry 3, loop 2's 1, loop 3's -8,
+        // the shared ones the differences
+        let current =
+            |name: &str| solution.branch_currents[circuit.element_index(name).unwrap()].clone();
+        assert_eq!(current("R1"), rational(3, 1));
+        assert_eq!(current("V1"), rational(-3, 1));
+        assert_eq!(current("R3"), rational(2, 1));
+        assert_eq!(current("R4"), rational(1, 1));
+        assert_eq!(current("R6"), rational(9, 1));
+        assert_eq!(current("V2"), rational(-9, 1));
+        assert_eq!(current("R8"), rational(-8, 1));
+
+        let drop =
+            |name: &str| solution.voltage_drops[circuit.element_index(name).unwrap()].clone();
+        assert_eq!(drop("R1"), rational(12, 1));
+        assert_eq!(drop("R6"), rational(9, 1));
+        assert_eq!(drop("V3"), rational(20, 1));
+
+        // Kirchhoff's current law at every node
+        for node in 0..circuit.nodes.len() {
+            let net = circuit.elements.iter().zip(&solution.branch_currents).fold(
+                rational(0, 1),
+                |acc, (e, i)| match (e.from == node, e.to == node) {
+                    (true, false) => acc - i.clone(),
+                    (false, true) => acc + i.clone(),
+                    _ => acc,
+                },
+            );
+            assert_eq!(net, rational(0, 1), "at node {}", circuit.nodes[node]);
+        }
+    }
+
+    #[test]
+    fn parses_netlist_values() {
+        assert_eq!(parse_value("4").unwrap(), rational(4, 1));
+        assert_eq!(parse_value("4.7k").unwrap(), rational(4700, 1));
+        assert_eq!(parse_value("2.2MEG").unwrap(), rational(2200000, 1));
+        assert_eq!(parse_value("10m").unwrap(), rational(1, 100));
+        assert_eq!(parse_value("-1.5").unwrap(), rational(-3, 2));
+        assert!(parse_value("k").is_err());
+        assert!(parse_value("4x").is_err());
+
+        assert!(parse_netlist("R1 a b").unwrap_err().starts_with("line 1"));
+        assert!(parse_netlist("Q1 a b 1")
+            .unwrap_err()
+            .contains("unsupported"));
+    }
+
+    #[test]
+    fn nodal_analysis_matches_mesh_analysis() {
+        // same circuit, with b0 as ground
+        let circuit = parse_netlist(&LAY_NETLIST.replace(" b0", " 0")).unwrap();
+
+        let mesh = circuit.mesh_analysis().unwrap();
+        let nodal = circuit.nodal_analysis().unwrap();
+
+        assert_eq!(nodal.branch_currents, mesh.branch_currents);
+        assert_eq!(nodal.voltage_drops, mesh.voltage_drops);
+        // the + terminal of V1 is 30 V above ground
+        assert_eq!(nodal.node_voltages[0], rational(30, 1));
+    }
+
+    #[test]
+    fn nodal_analysis_with_current_source() {
+        // 3 A pushed into a, which is connected to ground through 5 ohm, and to a 10 V source through another 5 ohm
+        let circuit = parse_netlist(
+            "
+I1 0 a 3
+R1 a 0 5
+R2 a b 5
+V1 b 0 10
+",
+        )
+        .unwrap();
+
+        let solution = circuit.nodal_analysis().unwrap();
+
+        // 3 = V(a) / 5 + (V(a) - 10) / 5
+        let a = circuit.nodes.iter().position(|n| n == "a").unwrap();
+        assert_eq!(solution.node_voltages[a], rational(25, 2));
+        // R1, R2, V1: 5/2 A to ground, the remaining 1/2 A through the voltage source
+        assert_eq!(
+            solution.branch_currents[1..],
+            [rational(5, 2), rational(1, 2), rational(1, 2)]
+        );
+        assert_eq!(solution.voltage_drops[0], rational(-25, 2));
+
+        assert!(circuit
+            .mesh_analysis()
+            .unwrap_err()
+            .contains("current source"));
+    }
+
+    #[test]
+    fn ac_analysis_of_low_pass_filter() {
+        let circuit = parse_netlist(
+            "
+V1 in 0 1
+R1 in out 1k
+C1 out 0 1u
+",
+        )
+        .unwrap();
+        // 1 / (2π RC)
+        let cutoff = 1.0 / (2.0 * PI * 1e3 * 1e-6);
 
-        let rref_a = a.rref();
-        println!("rref_a: {:?}", rref_a);
+        let solution = circuit.ac_analysis(cutoff).unwrap();
 
-        let c4 = rref_a.col(3);
-        println!(
-            "result: l1 current: {}, l2 current: {}, l3 current: {}",
-            c4[0], c4[1], c4[2]
+        // at the cutoff frequency, the capacitor's impedance is as large as the resistor's:
+        // the output is 1/sqrt(2) of the input, 45° behind
+        let out = circuit.nodes.iter().position(|n| n == "out").unwrap();
+        let (magnitude, phase) = polar(&solution.node_voltages[out]);
+        assert_relative_eq!(magnitude, 1.0 / 2f64.sqrt(), epsilon = 1e-9);
+        assert_relative_eq!(phase, -45.0, epsilon = 1e-9);
+        // the current through C1 leads the voltage across it by 90°
+        let (_, current_phase) = polar(&solution.branch_currents[2]);
+        assert_relative_eq!(current_phase, 45.0, epsilon = 1e-9);
+
+        let sweep = circuit
+            .frequency_sweep("V1", "out", &log_frequencies(1.0, 100e3, 10).unwrap())
+            .unwrap();
+        assert_eq!(sweep.len(), 51);
+        // flat well below the cutoff, then -20 dB per decade
+        assert_relative_eq!(sweep[0].magnitude_db, 0.0, epsilon = 1e-3);
+        assert_relative_eq!(
+            sweep[40].magnitude_db - sweep[50].magnitude_db,
+            20.0,
+            epsilon = 0.01
+        );
+        assert_relative_eq!(sweep[50].phase_degrees, -90.0, epsilon = 0.1);
+        let table = bode_table(&sweep);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 52);
+        assert_eq!(lines[0], "frequency (Hz) magnitude (dB)    phase (deg)");
+        assert_eq!(lines[1], "         1.000         -0.000         -0.360");
+        assert_eq!(lines[31], "      1000.000        -16.072        -80.957");
+        assert_eq!(lines[51], "    100000.000        -55.964        -89.909");
+
+        assert!(circuit
+            .nodal_analysis()
+            .unwrap_err()
+            .contains("ac analysis"));
+    }
+
+    #[test]
+    fn frequency_sweep_rejects_bad_input() {
+        assert!(log_frequencies(0.0, 100.0, 10)
+            .unwrap_err()
+            .contains("isn't positive"));
+        assert!(log_frequencies(100.0, 10.0, 10)
+            .unwrap_err()
+            .contains("below the start"));
+        assert!(log_frequencies(1.0, 100.0, 0)
+            .unwrap_err()
+            .contains("per decade"));
+        // a single frequency is fine
+        assert_eq!(log_frequencies(50.0, 50.0, 10), Ok(vec![50.0]));
+
+        let circuit = parse_netlist(
+            "
+V1 in 0 0
+R1 in out 1k
+C1 out 0 1u
+",
+        )
+        .unwrap();
+        assert_eq!(
+            circuit.frequency_sweep("V1", "out", &[50.0]),
+            Err("source 'V1' has zero amplitude".to_string())
+        );
+    }
+
+    #[test]
+    fn ac_analysis_keeps_tiny_currents() {
+        let circuit = parse_netlist(
+            "
+V1 in 0 1
+R1 in out 1MEG
+C1 out 0 1p
+",
+        )
+        .unwrap();
+
+        let solution = circuit.ac_analysis(1.0).unwrap();
+
+        // the capacitor's impedance (~160 GΩ) is much larger than the resistor's, so it gets almost all the voltage
+        // and the current is about 1 V / 160 GΩ, leading by 90°
+        let expected = 2.0 * PI * 1e-12;
+        let [source, resistor, capacitor] = &solution.branch_currents[..] else {
+            panic!("3 elements");
+        };
+        assert_relative_eq!(resistor.norm(), expected, max_relative = 1e-4);
+        assert_relative_eq!(polar(resistor).1, 90.0, epsilon = 0.1);
+        // KCL: the same current everywhere (the source's flows from + to - through it)
+        assert_relative_eq!((source + resistor).norm(), 0.0, epsilon = 1e-9 * expected);
+        assert_relative_eq!(
+            (resistor - capacitor).norm(),
+            0.0,
+            epsilon = 1e-9 * expected
         );
 
-        assert_relative_eq!(c4[0], 3.0, epsilon = 0.000000000000000000000000001);
-        assert_relative_eq!(c4[1], 1.0, epsilon = 0.000000000000000000000000001);
-        assert_relative_eq!(c4[2], -8.0, epsilon = 0.000000000000000000000000001);
+        // the capacitor's admittance is the only entry in its column, tiny but a pivot
+        let circuit = parse_netlist("I1 0 a 1p\nC1 a 0 1p").unwrap();
+        let solution = circuit.ac_analysis(1.0).unwrap();
+        let a = circuit.nodes.iter().position(|n| n == "a").unwrap();
+        // 1 pA / (j 2π 1 pF)
+        assert_relative_eq!(
+            solution.node_voltages[a].norm(),
+            1.0 / (2.0 * PI),
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn ac_analysis_matches_dc_for_resistors() {
+        let circuit = parse_netlist(&LAY_NETLIST.replace(" b0", " 0")).unwrap();
+
+        let dc = circuit.nodal_analysis().unwrap();
+        let ac = circuit.ac_analysis(50.0).unwrap();
+
+        for (ac, dc) in ac.branch_currents.iter().zip(&dc.branch_currents) {
+            assert_relative_eq!(ac.re, dc.to_f64(), epsilon = 1e-9);
+            assert_relative_eq!(ac.im, 0.0, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn nodal_analysis_needs_ground() {
+        let circuit = parse_netlist(LAY_NETLIST).unwrap();
+        assert!(circuit.nodal_analysis().unwrap_err().contains("ground"));
+    }
+
+    #[test]
+    fn rejects_loop_of_sources() {
+        let circuit = parse_netlist("V1 a b 5\nV2 a b 3\nR1 a b 1").unwrap();
+        assert!(circuit.mesh_analysis().is_err());
+        let circuit = parse_netlist("V1 a 0 5\nV2 a 0 3\nR1 a 0 1").unwrap();
+        assert!(circuit.nodal_analysis().is_err());
+    }
+
+    fn loops() -> [Loop; 3] {
+        let l1 = Loop {
+            resistance_l1: 11,
+            resistance_l2: -3,
+            resistance_l3: 0,
+            voltage: 30,
+        };
+        let l2 = Loop {
+            resistance_l1: -3,
+            resistance_l2: 6,
+            resistance_l3: -1,
+            voltage: 5,
+        };
+        let l3 = Loop {
+            resistance_l1: 0,
+            resistance_l2: -1,
+            resistance_l3: 3,
+            // -5 from loop 2 and -20 from loop 3 (sign due to direction)
+            voltage: -25,
+        };
+
+        [l1, l2, l3]
     }
 }
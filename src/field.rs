@@ -0,0 +1,158 @@
+use num_bigint::BigInt;
+use num_complex::Complex;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::fmt::Display;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// values we can row reduce: either exact (rationals) or approximate (floats)
+/// this is deliberately small - just what elimination needs, so adding a scalar type is cheap
+pub trait Field:
+    nalgebra::Scalar
+    + Display
+    + Zero
+    + One
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    /// whether the value should be treated as zero when looking for pivots
+    /// exact for rationals, tolerance based for floats
+    fn is_negligible(&self) -> bool;
+
+    /// like `is_negligible`, for an entry of a matrix column whose largest entry has magnitude `scale`.
+    /// absolute (the same as `is_negligible`) unless a field says otherwise
+    fn is_negligible_in(&self, _scale: f64) -> bool {
+        self.is_negligible()
+    }
+
+    /// used to pick the pivot in a column: the entry with largest magnitude wins, first one on ties
+    fn magnitude(&self) -> f64;
+
+    fn from_i64(value: i64) -> Self;
+
+    fn to_f64(&self) -> f64;
+}
+
+/// below this we consider a float 0 (e.g. 1e-17 left over after subtracting rows)
+pub const F64_TOLERANCE: f64 = 1e-10;
+
+impl Field for f64 {
+    fn is_negligible(&self) -> bool {
+        self.abs() < F64_TOLERANCE
+    }
+
+    fn magnitude(&self) -> f64 {
+        self.abs()
+    }
+
+    fn from_i64(value: i64) -> Self {
+        value as f64
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+}
+
+impl Field for BigRational {
+    fn is_negligible(&self) -> bool {
+        self.is_zero()
+    }
+
+    // with exact arithmetic there's no rounding error to minimize,
+    // so all non-zero pivots are equal and we pick the first one, like when reducing by hand
+    fn magnitude(&self) -> f64 {
+        if self.is_zero() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    fn from_i64(value: i64) -> Self {
+        BigRational::from_integer(BigInt::from(value))
+    }
+
+    fn to_f64(&self) -> f64 {
+        ToPrimitive::to_f64(self).unwrap_or_else(|| {
+            // only when numerator or denominator don't fit in f64, which we don't expect in practice
+            if self.is_negative() {
+                f64::NEG_INFINITY
+            } else {
+                f64::INFINITY
+            }
+        })
+    }
+}
+
+// for ac circuits (complex impedances). like f64, approximate
+impl Field for Complex<f64> {
+    fn is_negligible(&self) -> bool {
+        self.norm() < F64_TOLERANCE
+    }
+
+    // relative: in ac circuits a whole column can be tiny and still matter,
+    // e.g. the admittance of 1 pF at 1 Hz is ~6e-12
+    fn is_negligible_in(&self, scale: f64) -> bool {
+        self.norm() <= F64_TOLERANCE * scale
+    }
+
+    fn magnitude(&self) -> f64 {
+        self.norm()
+    }
+
+    fn from_i64(value: i64) -> Self {
+        Complex::new(value as f64, 0.0)
+    }
+
+    /// the real part
+    fn to_f64(&self) -> f64 {
+        self.re
+    }
+}
+
+/// shorthand for a fraction, e.g. `rational(1, 4)`
+pub fn rational(numerator: i64, denominator: i64) -> BigRational {
+    BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+}
+
+/// a plain decimal number like `-4.7`, exactly (no exponent or suffixes)
+pub fn parse_decimal(str: &str) -> Result<BigRational, String> {
+    let invalid = || format!("invalid number '{}'", str);
+    let (integer, fraction) = str.split_once('.').unwrap_or((str, ""));
+    let unsigned = integer.strip_prefix(['-', '+']).unwrap_or(integer);
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if !all_digits(unsigned) || !all_digits(fraction) || unsigned.len() + fraction.len() == 0 {
+        return Err(invalid());
+    }
+    // e.g. 4.7 is 47 / 10
+    let numerator: BigInt = format!("{}{}", integer, fraction)
+        .parse()
+        .map_err(|_| invalid())?;
+    Ok(BigRational::new(
+        numerator,
+        BigInt::from(10).pow(fraction.len() as u32),
+    ))
+}
+
+/// the smallest integer vector pointing in the same direction,
+/// i.e. multiplied by the lcm of the denominators and divided by the gcd of the result
+/// e.g. (1/4, 5/4, 3/4, 1) -> (1, 5, 3, 4)
+pub fn to_primitive_integers(values: &[BigRational]) -> Vec<BigInt> {
+    let lcm = values
+        .iter()
+        .fold(BigInt::one(), |acc, v| acc.lcm(v.denom()));
+    let integers: Vec<BigInt> = values
+        .iter()
+        .map(|v| v.numer() * (&lcm / v.denom()))
+        .collect();
+    let gcd = integers.iter().fold(BigInt::zero(), |acc, v| acc.gcd(v));
+    if gcd.is_zero() {
+        return integers;
+    }
+    integers.iter().map(|v| v / &gcd).collect()
+}
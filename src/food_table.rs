@@ -0,0 +1,263 @@
+use crate::diet::Food;
+use nalgebra::DMatrix;
+use std::fs;
+use std::path::Path;
+
+/// a food composition table: one row per food, one column per nutrient (amount per 100g)
+///
+/// the file looks like this (comma or tab separated, detected from the header):
+/// ```text
+/// name,cost,protein,carbs,fat,fiber
+/// "Milk, nonfat",1.0,36,52,0,0
+/// ```
+/// the first column is the food name. a column named "cost" is the price (per 100g), all others are nutrients.
+/// empty cells count as 0, lines starting with '#' are comments
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoodTable {
+    pub nutrients: Vec<String>,
+    pub foods: Vec<FoodRow>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoodRow {
+    pub name: String,
+    pub cost: Option<f64>,
+    /// in the order of the table's nutrients
+    pub per_100g: Vec<f64>,
+}
+
+pub fn load_food_table(path: &Path) -> Result<FoodTable, String> {
+    let str =
+        fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+    parse_food_table(&str)
+}
+
+pub fn parse_food_table(str: &str) -> Result<FoodTable, String> {
+    let mut lines = str
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    let (_, header) = lines.next().ok_or("empty food table")?;
+    let delimiter = if header.contains('\t') { '\t' } else { ',' };
+    let header = split_fields(header, delimiter);
+    if header.len() < 2 {
+        return Err(format!(
+            "expected a name and nutrient columns, got: {:?}",
+            header
+        ));
+    }
+    // the first column is always the name, whatever it's called
+    let cost_column = header
+        .iter()
+        .skip(1)
+        .position(|column| column.eq_ignore_ascii_case("cost"))
+        .map(|i| i + 1);
+    let nutrients: Vec<String> = header
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(i, _)| Some(*i) != cost_column)
+        .map(|(_, column)| column.clone())
+        .collect();
+
+    let mut foods = vec![];
+    for (index, line) in lines {
+        // 1 based, like editors show them
+        let line_number = index + 1;
+        let fields = split_fields(line, delimiter);
+        if fields.len() != header.len() {
+            return Err(format!(
+                "line {}: {} columns, expected {}",
+                line_number,
+                fields.len(),
+                header.len()
+            ));
+        }
+        let mut values = vec![];
+        for (column, field) in header.iter().zip(&fields).skip(1) {
+            let value = match field.as_str() {
+                "" => 0.0,
+                _ => field
+                    .parse::<f64>()
+                    .map_err(|_| format!("line {}: invalid {} '{}'", line_number, column, field))?,
+            };
+            values.push(value);
+        }
+        // values start at the second column
+        let cost = cost_column.map(|c| values.remove(c - 1));
+        foods.push(FoodRow {
+            name: fields[0].clone(),
+            cost,
+            per_100g: values,
+        });
+    }
+
+    Ok(FoodTable { nutrients, foods })
+}
+
+/// splits on the delimiter, except inside double quotes ("" is an escaped quote)
+pub fn split_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+impl FoodTable {
+    /// names are matched ignoring case
+    pub fn food(&self, name: &str) -> Result<&FoodRow, String> {
+        self.foods
+            .iter()
+            .find(|f| f.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown food '{}'", name))
+    }
+
+    pub fn nutrient_index(&self, name: &str) -> Result<usize, String> {
+        self.nutrients
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown nutrient '{}'", name))
+    }
+
+    /// one row per nutrient, one column per food, like the diet system in balance_diet
+    pub fn coefficient_matrix(
+        &self,
+        foods: &[&str],
+        nutrients: &[&str],
+    ) -> Result<DMatrix<f64>, String> {
+        let columns = foods
+            .iter()
+            .map(|name| self.food(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rows = nutrients
+            .iter()
+            .map(|name| self.nutrient_index(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(DMatrix::from_fn(rows.len(), columns.len(), |r, c| {
+            columns[c].per_100g[rows[r]]
+        }))
+    }
+
+    /// the selected foods for the diet optimizer, with the selected nutrients in order. foods without cost cost 0
+    pub fn diet_foods(&self, foods: &[&str], nutrients: &[&str]) -> Result<Vec<Food>, String> {
+        let matrix = self.coefficient_matrix(foods, nutrients)?;
+        foods
+            .iter()
+            .enumerate()
+            .map(|(c, name)| {
+                let row = self.food(name)?;
+                Ok(Food::new(
+                    &row.name,
+                    row.cost.unwrap_or(0.0),
+                    matrix.column(c).iter().copied().collect(),
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TABLE: &str = "\
+name,cost,protein,carbs,fat,sodium
+# the foods from balance_diet
+\"Milk, nonfat\",1.0,36,52,0,0.5
+soy,1.5,51,34,7,
+whey,2.0,13,74,1.1,0.2
+";
+
+    #[test]
+    fn parses_csv() {
+        let table = parse_food_table(TABLE).unwrap();
+
+        assert_eq!(table.nutrients, vec!["protein", "carbs", "fat", "sodium"]);
+        assert_eq!(table.foods.len(), 3);
+        assert_eq!(
+            table.foods[0],
+            FoodRow {
+                name: "Milk, nonfat".to_string(),
+                cost: Some(1.0),
+                per_100g: vec![36.0, 52.0, 0.0, 0.5],
+            }
+        );
+        // empty cell
+        assert_eq!(table.foods[1].per_100g[3], 0.0);
+    }
+
+    #[test]
+    fn parses_tsv_without_cost() {
+        let table = parse_food_table("name\tprotein\tfiber\noats\t17\t10.6\n").unwrap();
+
+        assert_eq!(table.nutrients, vec!["protein", "fiber"]);
+        assert_eq!(table.foods[0].cost, None);
+        assert_eq!(table.foods[0].per_100g, vec![17.0, 10.6]);
+    }
+
+    #[test]
+    fn name_column_called_cost_is_not_the_cost() {
+        let table = parse_food_table("cost,protein\noats,17\n").unwrap();
+
+        assert_eq!(table.nutrients, vec!["protein"]);
+        assert_eq!(table.foods[0].name, "oats");
+        assert_eq!(table.foods[0].cost, None);
+        assert_eq!(table.foods[0].per_100g, vec![17.0]);
+
+        let table = parse_food_table("cost,cost,protein\noats,0.5,17\n").unwrap();
+        assert_eq!(table.foods[0].cost, Some(0.5));
+        assert_eq!(table.foods[0].per_100g, vec![17.0]);
+    }
+
+    #[test]
+    fn builds_coefficient_matrix_for_selection() {
+        let table = parse_food_table(TABLE).unwrap();
+
+        let matrix = table
+            .coefficient_matrix(&["whey", "milk, nonfat"], &["fat", "protein"])
+            .unwrap();
+
+        assert_eq!(
+            matrix,
+            DMatrix::from_row_slice(2, 2, &[1.1, 0.0, 13.0, 36.0])
+        );
+
+        let foods = table.diet_foods(&["soy"], &["protein", "fat"]).unwrap();
+        assert_eq!(foods, vec![Food::new("soy", 1.5, vec![51.0, 7.0])]);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let table = parse_food_table(TABLE).unwrap();
+        assert_eq!(
+            table.coefficient_matrix(&["bread"], &["fat"]),
+            Err("unknown food 'bread'".to_string())
+        );
+        assert!(table.coefficient_matrix(&["soy"], &["zinc"]).is_err());
+
+        assert_eq!(
+            parse_food_table("name,protein\nsoy,lots\n"),
+            Err("line 2: invalid protein 'lots'".to_string())
+        );
+        assert!(parse_food_table("name,protein\nsoy,1,2\n")
+            .unwrap_err()
+            .contains("line 2"));
+    }
+}
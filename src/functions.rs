@@ -1,33 +1,84 @@
-use bevy::{math::Vec2, prelude::Gizmos, render::color::Color};
+use bevy::{
+    math::{Vec2, Vec3},
+    prelude::Gizmos,
+    render::color::Color,
+};
 
-// 2d version. todo refactor with 3d
-pub fn draw_line2d_fn<F>(
+/// draws the line a*x + b*y = c, also when it's vertical (b = 0). 2d version, for 3d see `draw_plane3d_general_form`
+/// does nothing if a and b are both 0 (that's not a line: either no point or the whole plane)
+pub fn draw_line2d_general_form(
     gizmos: &mut Gizmos,
-    range_start: i32,
-    range_end: i32,
-    step_size: usize,
-    scaling: f32,
-    function: F,
-) where
-    F: Fn(f32) -> f32,
-{
-    let x_scaling = scaling;
-    let z_scaling = scaling;
+    a: f32,
+    b: f32,
+    c: f32,
+    half_length: f32,
+    color: Color,
+) {
+    let normal = Vec2::new(a, b);
+    let norm_squared = normal.length_squared();
+    if norm_squared < f32::EPSILON {
+        return;
+    }
+
+    // the point of the line closest to the origin, and the line's direction (perpendicular to the normal)
+    let closest = normal * (c / norm_squared);
+    let direction = normal.perp().normalize();
 
-    let mut last_point = None;
+    gizmos.line_2d(
+        closest - direction * half_length,
+        closest + direction * half_length,
+        color,
+    );
+}
 
-    for x_int in (range_start..range_end).step_by(step_size) {
-        let x = x_int as f32;
-        let z = function(x);
+/// draws the points of a trajectory connected in order, marking each point with a circle
+pub fn draw_trajectory_2d(gizmos: &mut Gizmos, points: &[Vec2], point_radius: f32, color: Color) {
+    gizmos.linestrip_2d(points.iter().copied(), color);
+    for point in points {
+        gizmos.circle_2d(*point, point_radius, color);
+    }
+}
 
-        if let Some((last_x, last_z)) = last_point {
-            gizmos.line_2d(
-                Vec2::new(last_x * x_scaling, last_z * z_scaling),
-                Vec2::new(x * x_scaling, z * z_scaling),
-                Color::WHITE,
-            );
-        }
+/// bevy's y axis points up, but in math (and Lay's pictures) it's z: x, y, z -> x, z, -y (a rotation, so still right handed)
+pub fn to_world_3d(x: f32, y: f32, z: f32) -> Vec3 {
+    Vec3::new(x, z, -y)
+}
+
+/// draws the plane a*x + b*y + c*z = d as a square grid of lines around its point closest to the origin
+/// does nothing if a, b and c are all 0
+#[allow(clippy::too_many_arguments)]
+pub fn draw_plane3d_general_form(
+    gizmos: &mut Gizmos,
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    half_size: f32,
+    lines: usize,
+    color: Color,
+) {
+    let normal = Vec3::new(a, b, c);
+    let norm_squared = normal.length_squared();
+    if norm_squared < f32::EPSILON {
+        return;
+    }
 
-        last_point = Some((x, z));
+    let closest = normal * (d / norm_squared);
+    // two perpendicular directions in the plane
+    let (u, v) = normal.normalize().any_orthonormal_pair();
+    let world = |p: Vec3| to_world_3d(p.x, p.y, p.z);
+    let step = 2.0 * half_size / lines.max(1) as f32;
+    for i in 0..=lines {
+        let offset = -half_size + i as f32 * step;
+        gizmos.line(
+            world(closest + u * offset - v * half_size),
+            world(closest + u * offset + v * half_size),
+            color,
+        );
+        gizmos.line(
+            world(closest + v * offset - u * half_size),
+            world(closest + v * offset + u * half_size),
+            color,
+        );
     }
 }
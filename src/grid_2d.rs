@@ -1,30 +1,65 @@
+use crate::system_2d::WorldView;
 use bevy::prelude::*;
 
-#[allow(dead_code)]
+/// grid lines are at least this far apart on screen
+const MIN_GRID_PIXELS: f32 = 40.0;
+
 pub fn add_grid_2d_system(app: &mut App) {
     app.add_systems(Update, draw_lines);
 }
 
-fn draw_lines(mut gizmos: Gizmos) {
-    let half_range = 300;
-    let step_size = 10;
+/// fills the screen, with a spacing of 1, 2 or 5 times a power of 10 depending on the zoom
+fn draw_lines(mut gizmos: Gizmos, view: Res<WorldView>) {
+    let spacing = grid_spacing(view.pixels(MIN_GRID_PIXELS));
+    let visible = view.visible;
     let color = Color::DARK_GRAY;
 
-    for line_pos_int in (-half_range..half_range).step_by(step_size) {
-        let line_pos = line_pos_int as f32;
-
-        // vertical lines
+    // multiples of the spacing, so the lines stay in place while panning
+    let first_x = (visible.min.x / spacing).floor() as i64;
+    let last_x = (visible.max.x / spacing).ceil() as i64;
+    for i in first_x..=last_x {
+        let x = i as f32 * spacing;
         gizmos.line_2d(
-            Vec2::new(line_pos, -half_range as f32),
-            Vec2::new(line_pos, half_range as f32),
+            Vec2::new(x, visible.min.y),
+            Vec2::new(x, visible.max.y),
             color,
         );
-
-        // horizontal lines
+    }
+    let first_y = (visible.min.y / spacing).floor() as i64;
+    let last_y = (visible.max.y / spacing).ceil() as i64;
+    for i in first_y..=last_y {
+        let y = i as f32 * spacing;
         gizmos.line_2d(
-            Vec2::new(-half_range as f32, line_pos),
-            Vec2::new(half_range as f32, line_pos),
+            Vec2::new(visible.min.x, y),
+            Vec2::new(visible.max.x, y),
             color,
         );
     }
 }
+
+/// the smallest of 1, 2, 5 times 10^k that is at least `min_spacing`
+fn grid_spacing(min_spacing: f32) -> f32 {
+    let power = 10f32.powf(min_spacing.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * power)
+        .find(|spacing| *spacing >= min_spacing)
+        .unwrap_or(10.0 * power)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn spacing_is_1_2_or_5_times_power_of_10() {
+        assert_relative_eq!(grid_spacing(0.4), 0.5);
+        assert_relative_eq!(grid_spacing(1.0), 1.0);
+        assert_relative_eq!(grid_spacing(1.1), 2.0);
+        assert_relative_eq!(grid_spacing(3.0), 5.0);
+        assert_relative_eq!(grid_spacing(6.0), 10.0);
+        assert_relative_eq!(grid_spacing(0.013), 0.02, epsilon = 1e-7);
+        assert_relative_eq!(grid_spacing(1500.0), 2000.0, epsilon = 1e-3);
+    }
+}
@@ -16,7 +16,18 @@ pub struct GuiInput {
 #[derive(Component, Default)]
 pub struct TextInput;
 
+/// marker component for the text below the add button, e.g. to show parse errors
+#[derive(Component, Default)]
+pub struct GuiMessage;
+
+const ERROR_COLOR: Color = Color::rgb(1.0, 0.4, 0.4);
+
 pub fn setup_gui(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_gui(&mut commands, &asset_server, "Add a vector:");
+}
+
+/// the side panel: a label, the text input and the add button, with a message text below
+pub fn spawn_gui(commands: &mut Commands, asset_server: &AssetServer, label_text: &str) {
     let font = asset_server.load("fonts/FiraMono-Medium.ttf");
 
     let mut root = commands.spawn(NodeBundle {
@@ -43,7 +54,7 @@ pub fn setup_gui(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         },
         text: Text::from_section(
-            "Add a vector:".to_string(),
+            label_text.to_string(),
             TextStyle {
                 font: font.clone(),
                 font_size: 20.0,
@@ -84,7 +95,34 @@ pub fn setup_gui(mut commands: Commands, asset_server: Res<AssetServer>) {
         parent.spawn((TextInput, text_input));
     });
 
-    add_add_button(root, &font);
+    let message = TextBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            ..default()
+        },
+        text: Text::from_section(
+            "".to_string(),
+            TextStyle {
+                font: font.clone(),
+                font_size: 16.0,
+                color: ERROR_COLOR,
+            },
+        ),
+        ..default()
+    };
+
+    add_add_button(&mut root, &font);
+
+    root.with_children(|parent| {
+        parent.spawn((GuiMessage, message));
+    });
+}
+
+/// replaces the message below the add button, in red for errors. empty to clear it
+pub fn show_gui_message(text: &mut Text, message: &str, is_error: bool) {
+    let section = &mut text.sections[0];
+    section.value = message.to_string();
+    section.style.color = if is_error { ERROR_COLOR } else { Color::WHITE };
 }
 
 pub fn button_system(
@@ -149,7 +187,7 @@ pub fn listen_received_character_events(
     }
 }
 
-fn add_add_button(mut gui_root: EntityCommands, font: &Handle<Font>) {
+fn add_add_button(gui_root: &mut EntityCommands, font: &Handle<Font>) {
     let button_node = NodeBundle {
         style: Style {
             width: Val::Px(100.0),
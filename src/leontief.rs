@@ -0,0 +1,368 @@
+use crate::field::Field;
+use crate::food_table::split_fields;
+use crate::plu::plu;
+use nalgebra::{DMatrix, DVector};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+/// Leontief input-output model (like in Lay's linear algebra)
+///
+/// column j of the consumption matrix C is what sector j uses from each sector to produce one unit.
+/// producing x uses up Cx, leaving x - Cx for the final demand d (households, exports...),
+/// so the production that meets the demand exactly is the solution of (I - C)x = d
+#[derive(Debug, Clone, PartialEq)]
+pub struct Economy<T: Field> {
+    pub sectors: Vec<String>,
+    pub consumption: DMatrix<T>,
+    pub demand: DVector<T>,
+}
+
+/// an amount for each sector, e.g. what it has to produce
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectorAmounts<T: Field> {
+    pub sectors: Vec<String>,
+    pub amounts: Vec<T>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeumannApproximation<T: Field> {
+    /// d + Cd + C^2 d + ... + C^(iterations - 1) d
+    pub production: SectorAmounts<T>,
+    /// terms added
+    pub iterations: usize,
+    /// whether the last term got below the tolerance (else we stopped at the max iterations)
+    pub converged: bool,
+}
+
+impl<T: Field> Economy<T> {
+    pub fn new(sectors: Vec<String>, consumption: DMatrix<T>, demand: DVector<T>) -> Economy<T> {
+        assert_eq!(
+            consumption.shape(),
+            (sectors.len(), sectors.len()),
+            "need one row and column per sector"
+        );
+        assert_eq!(demand.len(), sectors.len(), "need one demand per sector");
+        Economy {
+            sectors,
+            consumption,
+            demand,
+        }
+    }
+
+    /// what each sector uses from all sectors to produce one unit
+    pub fn column_sums(&self) -> Vec<T> {
+        self.consumption
+            .column_iter()
+            .map(|column| column.iter().fold(T::zero(), |acc, c| acc + c.clone()))
+            .collect()
+    }
+
+    /// sectors that use at least one unit of inputs per unit they produce, or have negative entries
+    pub fn unproductive_sectors(&self) -> Vec<String> {
+        let is_negative = |value: &T| !value.is_negligible() && value.to_f64() < 0.0;
+        self.column_sums()
+            .into_iter()
+            .enumerate()
+            .filter(|(j, sum)| {
+                let slack = T::one() - sum.clone();
+                slack.is_negligible()
+                    || is_negative(&slack)
+                    || self.consumption.column(*j).iter().any(is_negative)
+            })
+            .map(|(j, _)| self.sectors[j].clone())
+            .collect()
+    }
+
+    /// C >= 0 with every column sum < 1. then (I - C)^-1 exists and is >= 0,
+    /// so any demand can be met with non-negative production.
+    /// (this is sufficient, not necessary: other economies can have a solution too)
+    pub fn is_productive(&self) -> bool {
+        self.unproductive_sectors().is_empty()
+    }
+
+    /// x = (I - C)^-1 d, solving (I - C)x = d (without computing the inverse)
+    pub fn production(&self) -> Result<SectorAmounts<T>, String> {
+        let unproductive = self.unproductive_sectors();
+        if !unproductive.is_empty() {
+            return Err(format!(
+                "not productive: {} use at least as much as they produce",
+                unproductive.join(", ")
+            ));
+        }
+        let x = plu(&self.leontief_matrix())
+            .solve(&self.demand)
+            .ok_or("I - C is singular")?;
+        Ok(self.amounts(x))
+    }
+
+    /// I - C
+    pub fn leontief_matrix(&self) -> DMatrix<T> {
+        let n = self.sectors.len();
+        DMatrix::from_fn(n, n, |r, c| {
+            let identity = if r == c { T::one() } else { T::zero() };
+            identity - self.consumption[(r, c)].clone()
+        })
+    }
+
+    /// (I - C)^-1 d = d + Cd + C^2 d + ... for productive economies: the demand, what producing it needs,
+    /// what producing that needs, and so on. we add terms until one is below `tolerance` (in every sector)
+    pub fn neumann_series(&self, tolerance: f64, max_iterations: usize) -> NeumannApproximation<T> {
+        let mut term = self.demand.clone();
+        let mut x = DVector::from_element(term.len(), T::zero());
+        let mut iterations = 0;
+        let mut converged = false;
+        while iterations < max_iterations {
+            x = x.zip_map(&term, |x, t| x + t);
+            iterations += 1;
+            if term.iter().all(|t| t.to_f64().abs() < tolerance) {
+                converged = true;
+                break;
+            }
+            // C * term
+            term = DVector::from_fn(term.len(), |r, _| {
+                (0..term.len()).fold(T::zero(), |acc, c| {
+                    acc + self.consumption[(r, c)].clone() * term[c].clone()
+                })
+            });
+        }
+        NeumannApproximation {
+            production: self.amounts(x),
+            iterations,
+            converged,
+        }
+    }
+
+    fn amounts(&self, values: DVector<T>) -> SectorAmounts<T> {
+        SectorAmounts {
+            sectors: self.sectors.clone(),
+            amounts: values.iter().cloned().collect(),
+        }
+    }
+}
+
+impl<T: Field> SectorAmounts<T> {
+    /// the amount of the sector with this name (ignoring case)
+    pub fn get(&self, sector: &str) -> Option<&T> {
+        self.sectors
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(sector))
+            .map(|i| &self.amounts[i])
+    }
+}
+
+impl<T: Field> Display for SectorAmounts<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let width = self.sectors.iter().map(|s| s.len()).max().unwrap_or(0);
+        for (sector, amount) in self.sectors.iter().zip(&self.amounts) {
+            writeln!(f, "{:<width$}  {}", sector, amount, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn load_economy(path: &Path) -> Result<Economy<f64>, String> {
+    let str =
+        fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+    parse_economy(&str)
+}
+
+/// an input-output table, comma or tab separated like the food tables:
+/// ```text
+/// sector,manufacturing,agriculture,services,demand
+/// manufacturing,.5,.4,.2,50
+/// agriculture,.2,.3,.1,30
+/// services,.1,.1,.3,20
+/// ```
+/// one row per sector, in the order of the columns. the row is what the sector delivers to each sector
+/// (per unit they produce), the last column its final demand
+pub fn parse_economy(str: &str) -> Result<Economy<f64>, String> {
+    let mut lines = str
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    let (_, header) = lines.next().ok_or("empty input-output table")?;
+    let delimiter = if header.contains('\t') { '\t' } else { ',' };
+    let header = split_fields(header, delimiter);
+    let sectors = match &header[..] {
+        [_, sectors @ .., demand]
+            if !sectors.is_empty() && demand.eq_ignore_ascii_case("demand") =>
+        {
+            sectors.to_vec()
+        }
+        _ => {
+            return Err(format!(
+                "expected a name column, the sectors and a demand column, got: {:?}",
+                header
+            ))
+        }
+    };
+
+    let n = sectors.len();
+    let mut consumption = DMatrix::zeros(n, n);
+    let mut demand = DVector::zeros(n);
+    let mut rows = 0;
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let fields = split_fields(line, delimiter);
+        if fields.len() != header.len() {
+            return Err(format!(
+                "line {}: {} columns, expected {}",
+                line_number,
+                fields.len(),
+                header.len()
+            ));
+        }
+        let Some(expected) = sectors.get(rows) else {
+            return Err(format!("line {}: more rows than sectors", line_number));
+        };
+        if !fields[0].eq_ignore_ascii_case(expected) {
+            return Err(format!(
+                "line {}: expected the row of {}, got {}",
+                line_number, expected, fields[0]
+            ));
+        }
+        for (column, field) in fields.iter().enumerate().skip(1) {
+            let value = field.parse::<f64>().map_err(|_| {
+                format!(
+                    "line {}: invalid {} '{}'",
+                    line_number, header[column], field
+                )
+            })?;
+            match column {
+                c if c <= n => consumption[(rows, c - 1)] = value,
+                _ => demand[rows] = value,
+            }
+        }
+        rows += 1;
+    }
+    if rows < n {
+        return Err(format!("no row for {}", sectors[rows..].join(", ")));
+    }
+
+    Ok(Economy::new(sectors, consumption, demand))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::field::rational;
+    use approx::assert_relative_eq;
+    use num_rational::BigRational;
+
+    const LAY_TABLE: &str = "\
+sector,manufacturing,agriculture,services,demand
+manufacturing,.5,.4,.2,50
+agriculture,.2,.3,.1,30
+services,.1,.1,.3,20
+";
+
+    // example from Lay's linear algebra (section 2.6), exact
+    fn lay_economy() -> Economy<BigRational> {
+        let sectors = ["manufacturing", "agriculture", "services"];
+        #[rustfmt::skip]
+        let consumption = DMatrix::from_row_slice(3, 3, &[
+            rational(5, 10), rational(4, 10), rational(2, 10),
+            rational(2, 10), rational(3, 10), rational(1, 10),
+            rational(1, 10), rational(1, 10), rational(3, 10),
+        ]);
+        let demand = DVector::from_row_slice(&[rational(50, 1), rational(30, 1), rational(20, 1)]);
+        Economy::new(
+            sectors.iter().map(|s| s.to_string()).collect(),
+            consumption,
+            demand,
+        )
+    }
+
+    #[test]
+    fn computes_production() {
+        let economy = lay_economy();
+        assert!(economy.is_productive());
+
+        let x = economy.production().unwrap();
+
+        // Lay: x ≈ (226, 119, 78)
+        assert_eq!(x.get("manufacturing"), Some(&rational(6100, 27)));
+        assert_relative_eq!(x.amounts[1].to_f64(), 119.0, epsilon = 0.5);
+        assert_relative_eq!(x.amounts[2].to_f64(), 78.0, epsilon = 0.5);
+        // x - Cx is exactly the demand
+        let x_vector = DVector::from_vec(x.amounts.clone());
+        assert_eq!(&x_vector - &economy.consumption * &x_vector, economy.demand);
+        assert_eq!(
+            x.to_string(),
+            "manufacturing  6100/27\nagriculture    3200/27\nservices       700/9\n"
+        );
+    }
+
+    #[test]
+    fn neumann_series_approaches_production() {
+        let economy = parse_economy(LAY_TABLE).unwrap();
+        let exact = economy.production().unwrap();
+
+        let approximation = economy.neumann_series(1e-6, 1000);
+
+        assert!(approximation.converged);
+        assert!(approximation.iterations > 10);
+        for (approximate, exact) in approximation.production.amounts.iter().zip(&exact.amounts) {
+            assert_relative_eq!(approximate, exact, epsilon = 1e-4);
+        }
+
+        let truncated = economy.neumann_series(1e-6, 3);
+        assert!(!truncated.converged);
+        assert_eq!(truncated.iterations, 3);
+    }
+
+    #[test]
+    fn neumann_series_converges_with_rationals() {
+        let economy = lay_economy();
+        let exact = economy.production().unwrap();
+
+        let approximation = economy.neumann_series(1e-6, 1000);
+
+        assert!(approximation.converged);
+        assert!(approximation.iterations < 1000);
+        for (approximate, exact) in approximation.production.amounts.iter().zip(&exact.amounts) {
+            // every term is positive, so the partial sums stay below the production
+            assert!(approximate < exact);
+            assert_relative_eq!(approximate.to_f64(), exact.to_f64(), epsilon = 1e-4);
+        }
+    }
+
+    #[test]
+    fn rejects_unproductive_economy() {
+        let mut economy = lay_economy();
+        // agriculture now uses 1.1 units per unit
+        economy.consumption[(1, 1)] = rational(9, 10);
+
+        assert_eq!(economy.unproductive_sectors(), vec!["agriculture"]);
+        assert!(economy.production().unwrap_err().contains("agriculture"));
+    }
+
+    #[test]
+    fn parses_table_with_sector_names() {
+        let economy = parse_economy(&LAY_TABLE.replace(',', "\t")).unwrap();
+
+        assert_eq!(
+            economy.sectors,
+            vec!["manufacturing", "agriculture", "services"]
+        );
+        assert_eq!(economy.consumption[(0, 1)], 0.4);
+        assert_eq!(economy.demand[2], 20.0);
+
+        assert_eq!(
+            parse_economy("sector,a,b\na,.1,1\n"),
+            Err(
+                "expected a name column, the sectors and a demand column, got: [\"sector\", \"a\", \"b\"]"
+                    .to_string()
+            )
+        );
+        assert!(parse_economy("sector,a,b,demand\nb,.1,.2,1\n")
+            .unwrap_err()
+            .contains("expected the row of a"));
+        assert_eq!(
+            parse_economy("sector,a,b,demand\na,.1,.2,1\n"),
+            Err("no row for b".to_string())
+        );
+    }
+}
@@ -0,0 +1,23 @@
+//! the linear algebra behind the gui: exact row reduction, linear systems, decompositions and their applications
+//! (chemical equations, diets, circuits, economies, ...), also used headless by the cli
+
+mod alg;
+pub mod balance_chem_eq;
+mod balance_diet;
+pub mod cli;
+#[cfg(test)]
+mod dense_matrix;
+pub mod diet;
+pub mod dynamical_system;
+pub mod electrical_network;
+pub mod field;
+pub mod food_table;
+pub mod leontief;
+pub mod line_equation;
+pub mod linear_system;
+pub mod markov;
+pub mod network_flow;
+pub mod nnls;
+pub mod plu;
+pub mod row_reduction;
+pub mod simplex;
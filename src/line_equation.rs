@@ -0,0 +1,297 @@
+use crate::linear_system::{LinearSystem, Solution};
+use nalgebra::{DMatrix, DVector};
+
+/// the line a x + b y = c
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineEquation {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinesRelation {
+    Intersect {
+        x: f64,
+        y: f64,
+    },
+    /// rank [A b] > rank A: no point is on both
+    Parallel,
+    /// rank [A b] = rank A = 1: every point of the line is on both
+    Coincident,
+}
+
+/// how two lines relate, from the solution set of their system (i.e. comparing ranks).
+/// unlike comparing slopes, this also works for vertical lines
+pub fn lines_relation(line1: &LineEquation, line2: &LineEquation) -> LinesRelation {
+    match lines_system(&[*line1, *line2]).solve() {
+        Solution::Unique(solution) => LinesRelation::Intersect {
+            x: solution[0],
+            y: solution[1],
+        },
+        Solution::Inconsistent => LinesRelation::Parallel,
+        Solution::Infinite { .. } => LinesRelation::Coincident,
+    }
+}
+
+/// the point closest to all lines (least squares), for when they have no common point
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeastSquaresPoint {
+    pub x: f64,
+    pub y: f64,
+    /// distance from the point to each line
+    pub distances: Vec<f64>,
+    /// the point on each line closest to the least squares point, where its distance ends
+    pub closest_points: Vec<(f64, f64)>,
+}
+
+/// all lines as one system, a row [a b | c] per line.
+/// with more than 2 lines it's overdetermined, and usually inconsistent
+pub fn lines_system(lines: &[LineEquation]) -> LinearSystem<f64> {
+    LinearSystem::new(
+        DMatrix::from_fn(lines.len(), 2, |r, c| match c {
+            0 => lines[r].a,
+            _ => lines[r].b,
+        }),
+        DVector::from_fn(lines.len(), |r, _| lines[r].c),
+    )
+}
+
+/// minimizes the sum of (a x + b y - c)^2 over the lines by solving the normal equations AᵀA x = Aᵀb.
+/// we scale each line to a² + b² = 1 first: then a x + b y - c is the (signed) distance to the line,
+/// so "2x - y = 3" and "4x - 2y = 6" count the same. None if all lines are parallel (AᵀA is singular then)
+pub fn least_squares_point(lines: &[LineEquation]) -> Option<LeastSquaresPoint> {
+    let normalized: Vec<LineEquation> = lines
+        .iter()
+        .map(|line| {
+            let norm = line.a.hypot(line.b);
+            LineEquation {
+                a: line.a / norm,
+                b: line.b / norm,
+                c: line.c / norm,
+            }
+        })
+        .collect();
+    let system = lines_system(&normalized);
+    let a_transposed = system.coefficients.transpose();
+    let normal_equations = LinearSystem::new(
+        &a_transposed * &system.coefficients,
+        &a_transposed * &system.constants,
+    );
+    let Solution::Unique(solution) = normal_equations.solve() else {
+        return None;
+    };
+    let (x, y) = (solution[0], solution[1]);
+
+    let residuals: Vec<f64> = normalized
+        .iter()
+        .map(|line| line.a * x + line.b * y - line.c)
+        .collect();
+    Some(LeastSquaresPoint {
+        x,
+        y,
+        distances: residuals.iter().map(|r| r.abs()).collect(),
+        // back along the (unit) normal by the signed distance
+        closest_points: normalized
+            .iter()
+            .zip(&residuals)
+            .map(|(line, r)| (x - r * line.a, y - r * line.b))
+            .collect(),
+    })
+}
+
+/// parses equations like "2x - y = 3", "y = 0.5x + 0.5" or "x = 2", moving x and y to the left and the constants to the right.
+/// coefficients can be written as "2x" or "2*x", terms can be on both sides
+pub fn parse_line_equation(str: &str) -> Result<LineEquation, String> {
+    let (left, right) = match str.split('=').collect::<Vec<_>>()[..] {
+        [left, right] => (left, right),
+        _ => return Err(format!("expected one '=', got: {}", str)),
+    };
+    let [left_x, left_y, left_constant] = parse_side(left)?;
+    let [right_x, right_y, right_constant] = parse_side(right)?;
+
+    let equation = LineEquation {
+        a: left_x - right_x,
+        b: left_y - right_y,
+        c: right_constant - left_constant,
+    };
+    if equation.a == 0.0 && equation.b == 0.0 {
+        return Err(format!("not a line, x and y cancel out: {}", str));
+    }
+    Ok(equation)
+}
+
+/// sums of the x coefficients, the y coefficients and the constants
+fn parse_side(side: &str) -> Result<[f64; 3], String> {
+    let side: String = side.chars().filter(|c| !c.is_whitespace()).collect();
+    if side.is_empty() {
+        return Err("missing expression on one side of '='".to_string());
+    }
+
+    // split before each + and -, keeping the sign with its term. except in exponents, like 1e-3
+    let mut terms = vec![];
+    let mut start = 0;
+    for (i, c) in side.char_indices() {
+        let mut previous = side[..i].chars().rev();
+        let in_exponent = matches!(previous.next(), Some('e' | 'E'))
+            && previous.next().is_some_and(|c| c.is_ascii_digit());
+        if (c == '+' || c == '-') && i > 0 && !in_exponent {
+            terms.push(&side[start..i]);
+            start = i;
+        }
+    }
+    terms.push(&side[start..]);
+
+    let mut sums = [0.0; 3];
+    for term in terms {
+        let invalid = || format!("invalid term '{}'", term);
+        let (sign, unsigned) = match term.strip_prefix('-') {
+            Some(rest) => (-1.0, rest),
+            None => (1.0, term.strip_prefix('+').unwrap_or(term)),
+        };
+        let (number, index) = match unsigned.strip_suffix(['x', 'X']) {
+            Some(number) => (number, 0),
+            None => match unsigned.strip_suffix(['y', 'Y']) {
+                Some(number) => (number, 1),
+                None => (unsigned, 2),
+            },
+        };
+        let number = match index {
+            2 => number,
+            _ => number.strip_suffix('*').unwrap_or(number),
+        };
+        let value = match number {
+            // "x" is "1x"
+            "" if index < 2 => 1.0,
+            _ => number.parse::<f64>().map_err(|_| invalid())?,
+        };
+        if !value.is_finite() {
+            return Err(invalid());
+        }
+        sums[index] += sign * value;
+    }
+    Ok(sums)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn line(a: f64, b: f64, c: f64) -> LineEquation {
+        LineEquation { a, b, c }
+    }
+
+    #[test]
+    fn parses_general_and_slope_intercept_forms() {
+        assert_eq!(parse_line_equation("2x - y = 3"), Ok(line(2.0, -1.0, 3.0)));
+        assert_eq!(
+            parse_line_equation("y = 0.5x + 0.5"),
+            Ok(line(-0.5, 1.0, 0.5))
+        );
+        assert_eq!(parse_line_equation("x = 2"), Ok(line(1.0, 0.0, 2.0)));
+        assert_eq!(parse_line_equation("-Y=-4"), Ok(line(0.0, -1.0, -4.0)));
+        // terms on both sides, repeated, with '*'
+        assert_eq!(
+            parse_line_equation("3 + 2*x = y + x - 1"),
+            Ok(line(1.0, -1.0, -4.0))
+        );
+    }
+
+    #[test]
+    fn parses_exponents() {
+        assert_eq!(parse_line_equation("1e-3x = 2"), Ok(line(1e-3, 0.0, 2.0)));
+        assert_eq!(
+            parse_line_equation("y = 2.5e+1x"),
+            Ok(line(-25.0, 1.0, 0.0))
+        );
+        assert_eq!(
+            parse_line_equation("2E-1x - 1e2 = y"),
+            Ok(line(0.2, -1.0, 100.0))
+        );
+    }
+
+    #[test]
+    fn classifies_pairs_of_lines() {
+        let relation = |first: &str, second: &str| {
+            lines_relation(
+                &parse_line_equation(first).unwrap(),
+                &parse_line_equation(second).unwrap(),
+            )
+        };
+
+        assert_eq!(
+            relation("y = 0.5x + 0.5", "y = 3 - 2x"),
+            LinesRelation::Intersect { x: 1.0, y: 1.0 }
+        );
+        // vertical
+        assert_eq!(
+            relation("x = 2", "y = x"),
+            LinesRelation::Intersect { x: 2.0, y: 2.0 }
+        );
+        assert_eq!(relation("x = 2", "x = -1"), LinesRelation::Parallel);
+        assert_eq!(relation("2x - y = 3", "y = 2x"), LinesRelation::Parallel);
+        assert_eq!(
+            relation("2x - y = 3", "y = 2x - 3"),
+            LinesRelation::Coincident
+        );
+    }
+
+    #[test]
+    fn finds_least_squares_point() {
+        let lines = ["x = 0", "y = 0", "x + y = 3"].map(|l| parse_line_equation(l).unwrap());
+
+        // a triangle: no common point
+        assert_eq!(lines_system(&lines).solve(), Solution::Inconsistent);
+        let point = least_squares_point(&lines).unwrap();
+
+        assert_relative_eq!(point.x, 0.75, epsilon = 1e-9);
+        assert_relative_eq!(point.y, 0.75, epsilon = 1e-9);
+        assert_relative_eq!(point.distances[0], 0.75, epsilon = 1e-9);
+        assert_relative_eq!(point.distances[2], 1.5 / 2f64.sqrt(), epsilon = 1e-9);
+        assert_relative_eq!(point.closest_points[0].0, 0.0, epsilon = 1e-9);
+        assert_relative_eq!(point.closest_points[2].0, 1.5, epsilon = 1e-9);
+        assert_relative_eq!(point.closest_points[2].1, 1.5, epsilon = 1e-9);
+
+        // scaling an equation doesn't change the point
+        let scaled = [
+            lines[0],
+            lines[1],
+            parse_line_equation("2x + 2y = 6").unwrap(),
+        ];
+        assert_relative_eq!(
+            least_squares_point(&scaled).unwrap().x,
+            0.75,
+            epsilon = 1e-9
+        );
+
+        // with a common point, that's the least squares point
+        let meeting = ["x = 1", "y = 1", "y = x"].map(|l| parse_line_equation(l).unwrap());
+        let point = least_squares_point(&meeting).unwrap();
+        assert_relative_eq!(point.x, 1.0, epsilon = 1e-9);
+        assert!(point.distances.iter().all(|d| *d < 1e-9));
+
+        let parallel = ["x = 1", "x = 2", "2x = 1"].map(|l| parse_line_equation(l).unwrap());
+        assert_eq!(least_squares_point(&parallel), None);
+    }
+
+    #[test]
+    fn reports_invalid_equations() {
+        assert_eq!(
+            parse_line_equation("2x - y"),
+            Err("expected one '=', got: 2x - y".to_string())
+        );
+        assert_eq!(
+            parse_line_equation("2z = 1"),
+            Err("invalid term '2z'".to_string())
+        );
+        assert_eq!(
+            parse_line_equation("x + = 1"),
+            Err("invalid term '+'".to_string())
+        );
+        assert!(parse_line_equation("x = ").is_err());
+        assert!(parse_line_equation("x = x + 1")
+            .unwrap_err()
+            .contains("not a line"));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::field::Field;
+use crate::row_reduction::{rref, rref_augmented, Rref};
+use nalgebra::{DMatrix, DVector};
+
+/// a system of linear equations Ax = b
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearSystem<T: Field> {
+    pub coefficients: DMatrix<T>,
+    pub constants: DVector<T>,
+}
+
+/// the solution set of a linear system, determined by comparing the rank of A and [A b]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Solution<T: Field> {
+    Unique(DVector<T>),
+    /// rank [A b] > rank A: a row like [0 0 0 | 1] appeared when reducing
+    Inconsistent,
+    /// x = particular + t1 * null_space_basis[0] + t2 * null_space_basis[1] + ..., for any t1, t2, ...
+    /// the particular solution is the one with all free variables set to 0
+    Infinite {
+        particular: DVector<T>,
+        null_space_basis: Vec<DVector<T>>,
+    },
+}
+
+impl<T: Field> LinearSystem<T> {
+    pub fn new(coefficients: DMatrix<T>, constants: DVector<T>) -> LinearSystem<T> {
+        assert_eq!(
+            coefficients.nrows(),
+            constants.len(),
+            "need one constant per equation"
+        );
+        LinearSystem {
+            coefficients,
+            constants,
+        }
+    }
+
+    /// homogeneous system Ax = 0
+    pub fn homogeneous(coefficients: DMatrix<T>) -> LinearSystem<T> {
+        let constants = DVector::from_element(coefficients.nrows(), T::zero());
+        LinearSystem::new(coefficients, constants)
+    }
+
+    pub fn unknowns_count(&self) -> usize {
+        self.coefficients.ncols()
+    }
+
+    /// [A b]
+    pub fn augmented(&self) -> DMatrix<T> {
+        let n = self.unknowns_count();
+        let mut augmented = self.coefficients.clone().insert_column(n, T::zero());
+        augmented.set_column(n, &self.constants);
+        augmented
+    }
+
+    pub fn solve(&self) -> Solution<T> {
+        let n = self.unknowns_count();
+        let reduced = rref_augmented(&self.augmented());
+
+        // a pivot in the constants column means rank [A b] = rank A + 1
+        if reduced.pivot_columns.last() == Some(&n) {
+            return Solution::Inconsistent;
+        }
+
+        // pivot variables take the value of the constants column, free variables are 0
+        let mut particular = DVector::from_element(n, T::zero());
+        for (row, &col) in reduced.pivot_columns.iter().enumerate() {
+            particular[col] = reduced.matrix[(row, n)].clone();
+        }
+
+        if reduced.rank() == n {
+            Solution::Unique(particular)
+        } else {
+            Solution::Infinite {
+                particular,
+                null_space_basis: null_space_from_rref(&reduced, n),
+            }
+        }
+    }
+}
+
+/// basis of the solutions of Ax = 0, one vector per free variable
+/// empty when the columns of A are linearly independent
+pub fn null_space<T: Field>(matrix: &DMatrix<T>) -> Vec<DVector<T>> {
+    null_space_from_rref(&rref(matrix), matrix.ncols())
+}
+
+/// `columns` lets us pass the rref of an augmented matrix, ignoring the constants column
+fn null_space_from_rref<T: Field>(reduced: &Rref<T>, columns: usize) -> Vec<DVector<T>> {
+    reduced
+        .free_columns
+        .iter()
+        .filter(|&&free| free < columns)
+        .map(|&free| {
+            // set this free variable to 1, the others to 0, and read the pivot variables from the rref
+            let mut v = DVector::from_element(columns, T::zero());
+            v[free] = T::one();
+            for (row, &pivot) in reduced.pivot_columns.iter().enumerate() {
+                v[pivot] = -reduced.matrix[(row, free)].clone();
+            }
+            v
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::field::rational;
+    use num_rational::BigRational;
+
+    fn rational_matrix(rows: usize, cols: usize, values: &[i64]) -> DMatrix<BigRational> {
+        DMatrix::from_row_slice(rows, cols, values).map(|v| rational(v, 1))
+    }
+
+    fn rational_vector(values: &[i64]) -> DVector<BigRational> {
+        DVector::from_row_slice(values).map(|v| rational(v, 1))
+    }
+
+    #[test]
+    fn unique_solution() {
+        #[rustfmt::skip]
+        let a = rational_matrix(3, 3, &[
+            1, 0, -3,
+            2, 2, 9,
+            0, 1, 5,
+        ]);
+        let b = rational_vector(&[8, 7, -2]);
+
+        let solution = LinearSystem::new(a, b).solve();
+
+        assert_eq!(solution, Solution::Unique(rational_vector(&[5, 3, -1])));
+    }
+
+    // the system where nalgebra's lu returned 5.25e15 and faer inf / NaN (see alg.rs)
+    #[test]
+    fn no_solutions() {
+        #[rustfmt::skip]
+        let a = rational_matrix(3, 3, &[
+            0, 1, 4,
+            1, 3, 5,
+            3, 7, 7,
+        ]);
+        let b = rational_vector(&[-5, -2, 6]);
+
+        let solution = LinearSystem::new(a, b).solve();
+
+        assert_eq!(solution, Solution::Inconsistent);
+    }
+
+    #[test]
+    fn no_solutions_floats() {
+        #[rustfmt::skip]
+        let a = DMatrix::from_row_slice(3, 3, &[
+            0.0, 1.0, 4.0,
+            1.0, 3.0, 5.0,
+            3.0, 7.0, 7.0,
+        ]);
+        let b = DVector::from_row_slice(&[-5.0, -2.0, 6.0]);
+
+        let solution = LinearSystem::new(a, b).solve();
+
+        assert_eq!(solution, Solution::Inconsistent);
+    }
+
+    #[test]
+    fn infinitely_many_solutions() {
+        // x1 + 2x2 + 3x3 = 6, 2x1 + 4x2 + 6x3 = 12, x1 + x2 + x3 = 3
+        #[rustfmt::skip]
+        let a = rational_matrix(3, 3, &[
+            1, 2, 3,
+            2, 4, 6,
+            1, 1, 1,
+        ]);
+        let b = rational_vector(&[6, 12, 3]);
+
+        let solution = LinearSystem::new(a.clone(), b.clone()).solve();
+
+        let Solution::Infinite {
+            particular,
+            null_space_basis,
+        } = solution
+        else {
+            panic!("expected infinitely many solutions, got: {:?}", solution);
+        };
+        assert_eq!(particular, rational_vector(&[0, 3, 0]));
+        assert_eq!(null_space_basis, vec![rational_vector(&[1, -2, 1])]);
+
+        // any combination solves the system
+        let x = particular + null_space_basis[0].clone() * rational(7, 2);
+        assert_eq!(a * x, b);
+    }
+
+    #[test]
+    fn null_space_of_independent_columns_is_empty() {
+        let a = rational_matrix(2, 2, &[1, 2, 3, 4]);
+        assert!(null_space(&a).is_empty());
+    }
+}
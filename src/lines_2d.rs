@@ -1,125 +1,334 @@
-use crate::functions::draw_line2d_fn;
+use crate::functions::draw_line2d_general_form;
+use crate::gui::{
+    button_system, listen_received_character_events, show_gui_message, spawn_gui, GuiInput,
+    GuiMessage, TextInput,
+};
+use crate::system_2d::WorldView;
 use bevy::prelude::*;
-use nalgebra::{ArrayStorage, Const, Matrix2, Vector2};
+use linear_alg::line_equation::{
+    least_squares_point, lines_relation, lines_system, parse_line_equation, LeastSquaresPoint,
+    LineEquation, LinesRelation,
+};
+use linear_alg::linear_system::Solution;
+use linear_alg::row_reduction::{row_reduce_traced, RowReductionTrace};
+use nalgebra::{DMatrix, Matrix2, Vector2};
 
-#[allow(dead_code)]
+/// draws any number of lines with their pairwise intersections. equations typed into the side panel (e.g. "2x - y = 3")
+/// are added, "clear" removes all of them.
+/// with 2 lines we also draw the column space of their system, with more and no common point their least squares point,
+/// with its distance to each line (what least squares minimizes, e.g. in a regression)
 pub fn add_lines_2d_system(app: &mut App) {
-    app.add_systems(Update, draw_lines);
+    app.add_systems(Startup, setup_lines).add_systems(
+        Update,
+        (
+            listen_received_character_events,
+            button_system,
+            listen_line_inputs,
+            show_lines_summary,
+            draw_lines,
+        ),
+    );
 }
 
-fn draw_lines(mut gizmos: Gizmos) {
-    let half_range = 800;
+/// in the order they were added
+#[derive(Resource)]
+struct Lines {
+    equations: Vec<LineEquation>,
+}
 
-    let line1 = |x| (-1.0 - x) / -2.0;
-    let line2 = |x| 3.0 - (x * 2.0);
+/// animates the row reduction of the system: every few seconds applies the next row operation and redraws the lines.
+/// each row of the augmented matrix is a line, so we see how the lines move while their intersection stays in place,
+/// ending with x = .. and y = .., i.e. a vertical and a horizontal line through the solution.
+pub fn add_row_reduction_2d_system(app: &mut App) {
+    app.add_systems(Startup, setup_row_reduction)
+        .add_systems(Update, (advance_row_reduction, draw_row_reduction));
+}
 
-    let scaling = 100.0;
-    draw_line2d_fn(&mut gizmos, -half_range, half_range, 10, scaling, line1);
-    draw_line2d_fn(&mut gizmos, -half_range, half_range, 10, scaling, line2);
+#[derive(Resource)]
+struct RowReductionAnimation {
+    trace: RowReductionTrace<f64>,
+    /// 0 is the initial matrix, trace.steps.len() the reduced one
+    step: usize,
+    timer: Timer,
+}
 
-    let matrix: MatrixWithResults = to_matrix(line1, line2);
-    let intersection = intersection(&matrix);
-    // println!("matrix: {:?} intersection: {:?}", matrix, intersection);
+fn setup_row_reduction(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_gui(&mut commands, &asset_server, "Row reduction:");
 
-    draw_intersection(&mut gizmos, intersection, scaling);
+    let [line1, line2] = initial_lines();
+    let augmented = DMatrix::from_row_slice(
+        2,
+        3,
+        &[line1.a, line1.b, line1.c, line2.a, line2.b, line2.c],
+    );
+    let trace = row_reduce_traced(&augmented);
 
-    // just for convenience, draw column space on same plot
-    // note that column space looks different depending on coefficient multipliers and row ordering,
-    // and that we derive these from closures,
-    // so entering here equations from somewhere else can render different column space vectors
-    draw_column_space(&mut gizmos, &matrix, scaling);
+    commands.insert_resource(RowReductionAnimation {
+        trace,
+        step: 0,
+        timer: Timer::from_seconds(2.0, TimerMode::Repeating),
+    });
 }
 
-fn draw_intersection(gizmos: &mut Gizmos, intersection: Intersection, scaling: f32) {
-    gizmos.circle_2d(
-        Vec2 {
-            x: intersection.x * scaling,
-            y: intersection.y * scaling,
-        },
-        10.0,
-        Color::WHITE,
+/// shows the op that led to the current matrix in the panel, and the matrix itself
+fn advance_row_reduction(
+    time: Res<Time>,
+    mut animation: ResMut<RowReductionAnimation>,
+    mut message: Query<&mut Text, With<GuiMessage>>,
+) {
+    let started = animation.is_added();
+    if animation.timer.tick(time.delta()).just_finished() {
+        // start over after showing the reduced matrix
+        animation.step = (animation.step + 1) % (animation.trace.steps.len() + 1);
+    } else if !started {
+        return;
+    }
+    let Ok(mut message) = message.get_single_mut() else {
+        return;
+    };
+    show_gui_message(&mut message, &row_reduction_step_text(&animation), false);
+}
+
+fn row_reduction_step_text(animation: &RowReductionAnimation) -> String {
+    let step = animation.step;
+    let total = animation.trace.steps.len();
+    let description = match step {
+        0 => "initial matrix".to_string(),
+        _ => format!(
+            "step {}/{}: {}",
+            step,
+            total,
+            animation.trace.steps[step - 1].op
+        ),
+    };
+    format!("{}{}", description, animation.trace.matrix_at(step))
+}
+
+fn draw_row_reduction(
+    mut gizmos: Gizmos,
+    animation: Res<RowReductionAnimation>,
+    view: Res<WorldView>,
+) {
+    let m = animation.trace.matrix_at(animation.step);
+
+    for row in m.row_iter() {
+        draw_line2d_general_form(
+            &mut gizmos,
+            row[0] as f32,
+            row[1] as f32,
+            row[2] as f32,
+            view.reach(),
+            Color::WHITE,
+        );
+    }
+
+    // row operations don't change the solution set, so this stays in place during the whole animation
+    let row = |r: usize| LineEquation {
+        a: m[(r, 0)],
+        b: m[(r, 1)],
+        c: m[(r, 2)],
+    };
+    if let LinesRelation::Intersect { x, y } = lines_relation(&row(0), &row(1)) {
+        draw_intersection(&mut gizmos, Vec2::new(x as f32, y as f32), &view);
+    }
+}
+
+fn initial_lines() -> [LineEquation; 2] {
+    let parse = |str| parse_line_equation(str).expect("valid initial line");
+    [parse("y = 0.5x + 0.5"), parse("y = 3 - 2x")]
+}
+
+fn setup_lines(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_gui(
+        &mut commands,
+        &asset_server,
+        "Add a line (e.g. 2x - y = 3) or clear:",
     );
+
+    commands.insert_resource(Lines {
+        equations: initial_lines().to_vec(),
+    });
 }
 
-fn intersection(matrix: &MatrixWithResults) -> Intersection {
-    // TODO no unwrap
-    let solution = matrix.m.lu().solve(&matrix.res).unwrap();
-    Intersection {
-        x: solution.x,
-        y: solution.y,
+/// parses the equations confirmed with the add button. errors go to the panel, the text stays for fixing it.
+/// (if it parses, `show_lines_summary` replaces the message)
+fn listen_line_inputs(
+    mut commands: Commands,
+    inputs: Query<(Entity, &GuiInput)>,
+    mut lines: ResMut<Lines>,
+    mut text_input: Query<&mut Text, (With<TextInput>, Without<GuiMessage>)>,
+    mut message: Query<&mut Text, (With<GuiMessage>, Without<TextInput>)>,
+) {
+    let Ok(mut message) = message.get_single_mut() else {
+        return;
+    };
+    let Ok(mut text_input) = text_input.get_single_mut() else {
+        return;
+    };
+    for (entity, input) in inputs.iter() {
+        // processed once
+        commands.entity(entity).despawn();
+
+        let text = input.text.trim();
+        let parsed = match text {
+            "clear" => Ok(None),
+            _ => parse_line_equation(text).map(Some),
+        };
+        match parsed {
+            Ok(equation) => {
+                match equation {
+                    Some(equation) => lines.equations.push(equation),
+                    None => lines.equations.clear(),
+                }
+                text_input.sections[0].value.clear();
+            }
+            Err(err) => show_gui_message(&mut message, &err, true),
+        }
     }
 }
 
-fn to_matrix<F1, F2>(line1: F1, line2: F2) -> MatrixWithResults
-where
-    F1: Fn(f32) -> f32,
-    F2: Fn(f32) -> f32,
-{
-    let entry1 = to_matrix_entry(line1);
-    let entry2 = to_matrix_entry(line2);
+/// whenever the lines change (also at the start), says in the panel whether / where they meet
+fn show_lines_summary(
+    lines: Res<Lines>,
+    mut message: Query<&mut Text, (With<GuiMessage>, Without<TextInput>)>,
+) {
+    if !lines.is_changed() {
+        return;
+    }
+    let Ok(mut message) = message.get_single_mut() else {
+        return;
+    };
+    show_gui_message(&mut message, &lines_summary(&lines.equations), false);
+}
 
-    MatrixWithResults {
-        m: Matrix2::new(
-            entry1.x, entry1.y, //
-            entry2.x, entry2.y, //
-        ),
-        res: Vector2::new(entry1.res, entry2.res),
+fn lines_summary(lines: &[LineEquation]) -> String {
+    match lines {
+        [] => "no lines".to_string(),
+        [_] => "1 line".to_string(),
+        [line1, line2] => match lines_relation(line1, line2) {
+            LinesRelation::Intersect { x, y } => format!("intersection: ({:.3}, {:.3})", x, y),
+            LinesRelation::Parallel => "parallel: no intersection".to_string(),
+            LinesRelation::Coincident => "same line: every point is an intersection".to_string(),
+        },
+        _ => match lines_system(lines).solve() {
+            Solution::Unique(x) => format!("all lines meet at ({:.3}, {:.3})", x[0], x[1]),
+            Solution::Infinite { .. } => "all the same line".to_string(),
+            Solution::Inconsistent => match least_squares_point(lines) {
+                Some(point) => {
+                    let distances: Vec<String> = point
+                        .distances
+                        .iter()
+                        .enumerate()
+                        .map(|(i, d)| format!("line {}: {:.3}", i + 1, d))
+                        .collect();
+                    format!(
+                        "no common point\nleast squares: ({:.3}, {:.3})\ndistances:\n{}",
+                        point.x,
+                        point.y,
+                        distances.join("\n")
+                    )
+                }
+                None => "all parallel: no common point".to_string(),
+            },
+        },
     }
 }
 
-#[derive(Debug)]
-struct MatrixEntry {
-    x: f32,
-    y: f32,
-    res: f32,
+fn draw_lines(mut gizmos: Gizmos, lines: Res<Lines>, view: Res<WorldView>) {
+    let lines = &lines.equations;
+
+    // lines that are the same as another one are highlighted, since every point of them solves both
+    let mut coincident = vec![false; lines.len()];
+    let mut intersections = vec![];
+    for i in 0..lines.len() {
+        for j in i + 1..lines.len() {
+            match lines_relation(&lines[i], &lines[j]) {
+                LinesRelation::Intersect { x, y } => {
+                    intersections.push(Vec2::new(x as f32, y as f32))
+                }
+                LinesRelation::Coincident => {
+                    coincident[i] = true;
+                    coincident[j] = true;
+                }
+                // nothing to mark
+                LinesRelation::Parallel => {}
+            }
+        }
+    }
+
+    for (line, coincident) in lines.iter().zip(coincident) {
+        draw_line2d_general_form(
+            &mut gizmos,
+            line.a as f32,
+            line.b as f32,
+            line.c as f32,
+            view.reach(),
+            if coincident {
+                Color::YELLOW
+            } else {
+                Color::WHITE
+            },
+        );
+    }
+    for intersection in intersections {
+        draw_intersection(&mut gizmos, intersection, &view);
+    }
+
+    if let [line1, line2] = lines[..] {
+        // just for convenience, draw column space on same plot
+        // note that column space looks different depending on coefficient multipliers and row ordering,
+        // so e.g. "2x - y = 3" and "y = 2x - 3" render different column space vectors for the same line
+        let matrix = MatrixWithResults {
+            m: Matrix2::new(
+                line1.a as f32,
+                line1.b as f32,
+                line2.a as f32,
+                line2.b as f32,
+            ),
+            res: Vector2::new(line1.c as f32, line2.c as f32),
+        };
+        draw_column_space(&mut gizmos, &matrix);
+    } else if lines.len() > 2 && lines_system(lines).solve() == Solution::Inconsistent {
+        if let Some(point) = least_squares_point(lines) {
+            draw_least_squares_point(&mut gizmos, &point, &view);
+        }
+    }
 }
 
-#[derive(Debug)]
-struct MatrixWithResults {
-    m: Matrix2<f32>,
-    res: Vector2<f32>,
+/// the point, and a segment from it to the closest point of each line
+fn draw_least_squares_point(gizmos: &mut Gizmos, point: &LeastSquaresPoint, view: &WorldView) {
+    let center = Vec2::new(point.x as f32, point.y as f32);
+    for (x, y) in &point.closest_points {
+        gizmos.line_2d(center, Vec2::new(*x as f32, *y as f32), Color::RED);
+    }
+    gizmos.circle_2d(center, view.pixels(6.0), Color::GREEN);
 }
 
-#[derive(Debug)]
-struct Line {
-    m: f32,
-    b: f32,
+fn draw_intersection(gizmos: &mut Gizmos, intersection: Vec2, view: &WorldView) {
+    gizmos.circle_2d(intersection, view.pixels(10.0), Color::WHITE);
 }
 
 #[derive(Debug)]
-struct Intersection {
-    x: f32,
-    y: f32,
-}
-
-fn to_matrix_entry<F>(line_closure: F) -> MatrixEntry
-where
-    F: Fn(f32) -> f32,
-{
-    let coefficients = to_line_coefficients(line_closure);
-    // println!("coefficients: {:?}", coefficients);
-    MatrixEntry {
-        x: -coefficients.m,
-        y: 1.0,
-        res: coefficients.b,
-    }
+struct MatrixWithResults {
+    m: Matrix2<f32>,
+    res: Vector2<f32>,
 }
 
-fn draw_column_space(gizmos: &mut Gizmos, matrix: &MatrixWithResults, scaling: f32) {
+fn draw_column_space(gizmos: &mut Gizmos, matrix: &MatrixWithResults) {
     let col1 = matrix.m.column(0);
     let col2 = matrix.m.column(1);
 
     let v1 = Vec2 {
-        x: col1[0] * scaling,
-        y: col1[1] * scaling,
+        x: col1[0],
+        y: col1[1],
     };
     let v2 = Vec2 {
-        x: col2[0] * scaling,
-        y: col2[1] * scaling,
+        x: col2[0],
+        y: col2[1],
     };
     let v_sum = Vec2 {
-        x: matrix.res[0] * scaling,
-        y: matrix.res[1] * scaling,
+        x: matrix.res[0],
+        y: matrix.res[1],
     };
 
     let origin = Vec2 { x: 0.0, y: 0.0 };
@@ -131,28 +340,3 @@ fn draw_column_space(gizmos: &mut Gizmos, matrix: &MatrixWithResults, scaling: f
     gizmos.arrow_2d(v1, v_sum, Color::BLACK);
     gizmos.arrow_2d(v2, v_sum, Color::BLACK);
 }
-
-fn to_line_coefficients<F>(line_closure: F) -> Line
-where
-    F: Fn(f32) -> f32,
-{
-    let x1 = 1.0;
-    let y1 = line_closure(x1);
-    let x2 = 2.0;
-    let y2 = line_closure(x2);
-
-    let a: nalgebra::Matrix<f32, Const<2>, Const<2>, ArrayStorage<f32, 2, 2>> = Matrix2::new(
-        x1, 1.0, //
-        x2, 1.0, //
-    );
-
-    let b = Vector2::new(y1, y2);
-
-    // TODO no unwrap
-    let solution = a.lu().solve(&b).unwrap();
-
-    Line {
-        m: solution.x,
-        b: solution.y,
-    }
-}
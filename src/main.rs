@@ -1,37 +1,76 @@
 //! This example demonstrates Bevy's immediate mode drawing API intended for visual debugging.
 
-mod alg;
-mod balance_chem_eq;
-mod balance_diet;
-mod electrical_network;
+mod dynamical_system_2d;
 mod functions;
 mod grid_2d;
 mod gui;
 mod lines_2d;
+mod planes_3d;
 mod system_2d;
+mod system_3d;
 mod vectors_2d_system;
 use bevy::app::App;
+use dynamical_system_2d::add_dynamical_system_2d_system;
 use grid_2d::add_grid_2d_system;
-#[allow(unused_imports)]
+use linear_alg::cli;
 use lines_2d::add_lines_2d_system;
+use lines_2d::add_row_reduction_2d_system;
+use planes_3d::add_planes_3d_system;
 use system_2d::add_2d_axes;
-#[allow(unused_imports)]
 use system_2d::add_2d_space;
+use system_3d::add_3d_space;
 #[allow(unused_imports)]
 use vectors_2d_system::add_vectors_2d_system;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let create: fn(&mut App) = match args.as_slice() {
+        [] => create_2d,
+        [mode] if mode == "3d" => create_3d,
+        [mode] if mode == "row-reduction" => create_row_reduction_2d,
+        [mode] if mode == "dynamics" => create_dynamical_system_2d,
+        // run a headless subcommand instead of the gui, e.g. `linear_alg balance "H2 + O2 -> H2O"`
+        _ => {
+            match cli::run(&args) {
+                Ok(output) => println!("{}", output),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+    };
     let app = &mut App::new();
-    create_2d(app);
+    create(app);
     app.run();
 }
 
-#[allow(dead_code)]
 fn create_2d(app: &mut App) {
+    add_2d_plane(app);
+    add_lines_2d_system(app);
+}
+
+fn create_row_reduction_2d(app: &mut App) {
+    add_2d_plane(app);
+    add_row_reduction_2d_system(app);
+}
+
+fn create_dynamical_system_2d(app: &mut App) {
+    add_2d_plane(app);
+    add_dynamical_system_2d_system(app);
+}
+
+/// the space with grid and axes, for the 2d modes
+fn add_2d_plane(app: &mut App) {
     add_2d_space(app);
     // add_vectors_2d_system(app);
     add_grid_2d_system(app);
     // grid completely hiding axes so draw axes on top.
     add_2d_axes(app);
-    add_lines_2d_system(app);
+}
+
+fn create_3d(app: &mut App) {
+    add_3d_space(app);
+    add_planes_3d_system(app);
 }
@@ -0,0 +1,231 @@
+use crate::field::Field;
+use crate::linear_system::null_space;
+use nalgebra::{DMatrix, DVector};
+
+/// a Markov chain given by its stochastic matrix P, like in Lay's linear algebra:
+/// entry (i, j) is the probability of going from state j to state i, so each column sums to 1,
+/// and a distribution (probability vector) x moves to Px in one step
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkovChain<T: Field> {
+    pub p: DMatrix<T>,
+}
+
+impl<T: Field> MarkovChain<T> {
+    /// checks that P is square, with entries >= 0 and columns summing to 1
+    pub fn new(p: DMatrix<T>) -> Result<MarkovChain<T>, String> {
+        if !p.is_square() {
+            return Err(format!(
+                "a stochastic matrix is square, got {}x{}",
+                p.nrows(),
+                p.ncols()
+            ));
+        }
+        for (j, column) in p.column_iter().enumerate() {
+            if let Some(negative) = column.iter().find(|v| is_negative(*v)) {
+                return Err(format!("column {} has a negative entry: {}", j, negative));
+            }
+            let sum = column.iter().fold(T::zero(), |acc, v| acc + v.clone());
+            if !(sum.clone() - T::one()).is_negligible() {
+                return Err(format!("column {} sums to {}, not 1", j, sum));
+            }
+        }
+        Ok(MarkovChain { p })
+    }
+
+    pub fn states_count(&self) -> usize {
+        self.p.nrows()
+    }
+
+    /// the distribution after one step: Px
+    pub fn step(&self, x: &DVector<T>) -> DVector<T> {
+        assert_eq!(x.len(), self.states_count(), "need one entry per state");
+        DVector::from_fn(x.len(), |r, _| {
+            (0..x.len()).fold(T::zero(), |acc, c| {
+                acc + self.p[(r, c)].clone() * x[c].clone()
+            })
+        })
+    }
+
+    /// x0, x1 = P x0, ..., xn
+    pub fn distributions(&self, x0: &DVector<T>, n: usize) -> Vec<DVector<T>> {
+        let mut distributions = vec![x0.clone()];
+        for _ in 0..n {
+            let next = self.step(distributions.last().expect("starts with x0"));
+            distributions.push(next);
+        }
+        distributions
+    }
+
+    /// xn = P^n x0
+    pub fn distribution_after(&self, x0: &DVector<T>, n: usize) -> DVector<T> {
+        (0..n).fold(x0.clone(), |x, _| self.step(&x))
+    }
+
+    /// the probability vector q with Pq = q, i.e. the solution of (P - I)x = 0 whose entries add up to 1.
+    /// every stochastic matrix has one, but it's only unique if the null space of P - I is a line
+    /// (e.g. for regular chains, which also converge to it from any start)
+    pub fn steady_state(&self) -> Result<DVector<T>, String> {
+        let n = self.states_count();
+        let p_minus_i = DMatrix::from_fn(n, n, |r, c| {
+            let identity = if r == c { T::one() } else { T::zero() };
+            self.p[(r, c)].clone() - identity
+        });
+        let basis = null_space(&p_minus_i);
+        let [x] = &basis[..] else {
+            return Err(format!(
+                "no unique steady state: (P - I)x = 0 has {} independent solutions",
+                basis.len()
+            ));
+        };
+        // all entries have the same sign here, so the sum isn't 0
+        let sum = x.iter().fold(T::zero(), |acc, v| acc + v.clone());
+        Ok(x.map(|v| v / sum.clone()))
+    }
+
+    /// whether some power of P has only positive entries: then there's a unique steady state,
+    /// and every distribution converges to it
+    ///
+    /// only the zero pattern matters, and if P is regular, P^k is positive for some k <= (n - 1)^2 + 1 (Wielandt),
+    /// so we follow which entries are non-zero in P, P^2, ... up to there
+    pub fn is_regular(&self) -> bool {
+        let n = self.states_count();
+        let pattern = self.p.map(|v| !v.is_negligible());
+        let mut power = pattern.clone();
+        for _ in 0..n.saturating_sub(1).pow(2) + 1 {
+            if power.iter().all(|positive| *positive) {
+                return true;
+            }
+            power = DMatrix::from_fn(n, n, |r, c| {
+                (0..n).any(|k| pattern[(r, k)] && power[(k, c)])
+            });
+        }
+        false
+    }
+
+    /// states that can't be left: column j is the j-th unit vector
+    pub fn absorbing_states(&self) -> Vec<usize> {
+        (0..self.states_count())
+            .filter(|&j| (self.p[(j, j)].clone() - T::one()).is_negligible())
+            .collect()
+    }
+
+    /// whether there are absorbing states and every state can reach one of them
+    /// (so the chain eventually ends up in one)
+    pub fn is_absorbing(&self) -> bool {
+        let absorbing = self.absorbing_states();
+        if absorbing.is_empty() {
+            return false;
+        }
+        // walk backwards from the absorbing states
+        let n = self.states_count();
+        let mut reaches = vec![false; n];
+        let mut stack = absorbing;
+        while let Some(to) = stack.pop() {
+            if reaches[to] {
+                continue;
+            }
+            reaches[to] = true;
+            stack.extend(
+                (0..n).filter(|&from| !self.p[(to, from)].is_negligible() && !reaches[from]),
+            );
+        }
+        reaches.into_iter().all(|r| r)
+    }
+}
+
+fn is_negative<T: Field>(value: &T) -> bool {
+    !value.is_negligible() && value.to_f64() < 0.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::field::rational;
+    use approx::assert_relative_eq;
+    use num_rational::BigRational;
+
+    fn tenths(rows: usize, values: &[i64]) -> DMatrix<BigRational> {
+        DMatrix::from_row_slice(rows, values.len() / rows, values).map(|v| rational(v, 10))
+    }
+
+    // example from Lay's linear algebra (section 4.9)
+    fn lay_chain() -> MarkovChain<BigRational> {
+        #[rustfmt::skip]
+        let p = tenths(3, &[
+            5, 2, 3,
+            3, 8, 3,
+            2, 0, 4,
+        ]);
+        MarkovChain::new(p).unwrap()
+    }
+
+    #[test]
+    fn finds_steady_state() {
+        let chain = lay_chain();
+
+        let q = chain.steady_state().unwrap();
+
+        assert_eq!(q, tenths(3, &[3, 6, 1]).column(0).into_owned());
+        assert_eq!(chain.step(&q), q);
+        assert!(chain.is_regular());
+    }
+
+    #[test]
+    fn simulates_distributions() {
+        let chain = lay_chain();
+        let x0 = DVector::from_row_slice(&[rational(1, 1), rational(0, 1), rational(0, 1)]);
+
+        let distributions = chain.distributions(&x0, 20);
+
+        assert_eq!(distributions.len(), 21);
+        assert_eq!(
+            distributions[1],
+            tenths(3, &[5, 3, 2]).column(0).into_owned()
+        );
+        assert_eq!(chain.distribution_after(&x0, 20), distributions[20]);
+        // regular: converges to the steady state
+        let q = chain.steady_state().unwrap();
+        for i in 0..3 {
+            assert_relative_eq!(distributions[20][i].to_f64(), q[i].to_f64(), epsilon = 1e-4);
+        }
+    }
+
+    #[test]
+    fn detects_absorbing_states() {
+        // a random walk on 0..3 that stops at both ends
+        #[rustfmt::skip]
+        let p = tenths(4, &[
+            10, 5, 0, 0,
+            0, 0, 5, 0,
+            0, 5, 0, 0,
+            0, 0, 5, 10,
+        ]);
+        let chain = MarkovChain::new(p).unwrap();
+
+        assert_eq!(chain.absorbing_states(), vec![0, 3]);
+        assert!(chain.is_absorbing());
+        assert!(!chain.is_regular());
+        assert!(chain.steady_state().unwrap_err().contains("2 independent"));
+
+        // a cycle: never absorbed, and not regular either (P^k is never positive)
+        let cycle = MarkovChain::new(tenths(2, &[0, 10, 10, 0])).unwrap();
+        assert!(cycle.absorbing_states().is_empty());
+        assert!(!cycle.is_absorbing());
+        assert!(!cycle.is_regular());
+    }
+
+    #[test]
+    fn validates_stochastic_matrix() {
+        assert!(MarkovChain::new(tenths(2, &[5, 5, 5, 6]))
+            .unwrap_err()
+            .contains("column 1 sums to"));
+        assert!(MarkovChain::new(tenths(2, &[15, 0, -5, 10]))
+            .unwrap_err()
+            .contains("negative"));
+        assert!(MarkovChain::new(DMatrix::from_element(2, 3, 0.5)).is_err());
+
+        // floats within tolerance are fine
+        let p = DMatrix::from_row_slice(2, 2, &[0.1 + 0.2, 0.5, 0.7, 0.5]);
+        assert!(MarkovChain::new(p).is_ok());
+    }
+}
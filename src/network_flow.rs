@@ -0,0 +1,424 @@
+use crate::field::{parse_decimal, Field};
+use crate::linear_system::{LinearSystem, Solution};
+use crate::row_reduction::rref;
+use crate::simplex::{Constraint, LinearProgram, LpSolution, Relation};
+use nalgebra::{DMatrix, DVector};
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+/// a directed graph of flows (e.g. traffic through intersections, like in Lay's linear algebra).
+/// at every node, what flows in equals what flows out. nodes are identified by their index in `nodes`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowNetwork {
+    pub nodes: Vec<String>,
+    pub edges: Vec<Edge>,
+    /// names of the unknown flows, in order of appearance. these are the columns of the conservation system
+    pub unknowns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub flow: Flow,
+    /// upper bound for an unknown flow
+    pub capacity: Option<BigRational>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Flow {
+    Known(BigRational),
+    /// index in the network's unknowns
+    Unknown(usize),
+}
+
+/// the possible values of an unknown flow, given the bounds. None where it can grow (or shrink) without limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowRange {
+    pub name: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// name of the node for everything outside the network: flows from / to it enter / leave the network,
+/// and it has no conservation equation
+pub const OUTSIDE: &str = "*";
+
+/// parses lines like `A -> B x1` (unknown flow), `A -> B 300` (known flow), or `A -> B x1 <= 500` (unknown with capacity).
+/// `* -> A 300` enters the network at A, `A -> * 300` leaves it. using a name twice means it's the same flow.
+/// lines starting with '#' are comments
+pub fn parse_flow_network(str: &str) -> Result<FlowNetwork, String> {
+    let mut network = FlowNetwork {
+        nodes: vec![],
+        edges: vec![],
+        unknowns: vec![],
+    };
+    for (index, line) in str.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| format!("line {}: {}", index + 1, message);
+
+        let (from, rest) = line
+            .split_once("->")
+            .ok_or_else(|| error(format!("expected 'from -> to flow', got: {}", line)))?;
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        let (to, flow, capacity) = match fields[..] {
+            [to, flow] => (to, flow, None),
+            [to, flow, "<=", capacity] => (to, flow, Some(capacity)),
+            _ => {
+                return Err(error(format!(
+                    "expected 'from -> to flow [<= capacity]', got: {}",
+                    line
+                )))
+            }
+        };
+        let from = network.node(from.trim());
+        let to = network.node(to);
+        if from == to {
+            return Err(error(format!("{} flows into itself", network.nodes[from])));
+        }
+
+        let flow = match parse_decimal(flow) {
+            Ok(value) => Flow::Known(value),
+            Err(_) if flow.starts_with(|c: char| c.is_alphabetic()) => {
+                Flow::Unknown(network.unknown(flow))
+            }
+            Err(_) => return Err(error(format!("invalid flow '{}'", flow))),
+        };
+        let capacity = match (capacity, &flow) {
+            (None, _) => None,
+            (Some(capacity), Flow::Unknown(_)) => Some(parse_decimal(capacity).map_err(error)?),
+            (Some(_), Flow::Known(_)) => {
+                return Err(error("only unknown flows can have a capacity".to_string()))
+            }
+        };
+        network.edges.push(Edge {
+            from,
+            to,
+            flow,
+            capacity,
+        });
+    }
+    Ok(network)
+}
+
+impl FlowNetwork {
+    /// index of the node with this name, added if new
+    fn node(&mut self, name: &str) -> usize {
+        index_or_push(&mut self.nodes, name)
+    }
+
+    fn unknown(&mut self, name: &str) -> usize {
+        index_or_push(&mut self.unknowns, name)
+    }
+
+    /// the nodes with a conservation equation, i.e. all except the outside
+    pub fn inner_nodes(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&n| self.nodes[n] != OUTSIDE)
+            .collect()
+    }
+
+    /// one equation per inner node: unknown inflows - unknown outflows = known outflows - known inflows
+    pub fn conservation_system(&self) -> LinearSystem<BigRational> {
+        let nodes = self.inner_nodes();
+        let mut a = DMatrix::from_element(nodes.len(), self.unknowns.len(), BigRational::zero());
+        let mut b = DVector::from_element(nodes.len(), BigRational::zero());
+        for (row, &node) in nodes.iter().enumerate() {
+            for edge in &self.edges {
+                // +1 for flowing in, -1 for flowing out
+                let sign = match (edge.to == node, edge.from == node) {
+                    (true, _) => BigRational::one(),
+                    (_, true) => -BigRational::one(),
+                    _ => continue,
+                };
+                match &edge.flow {
+                    Flow::Unknown(j) => a[(row, *j)] = a[(row, *j)].clone() + sign,
+                    Flow::Known(value) => b[row] = b[row].clone() - sign * value.clone(),
+                }
+            }
+        }
+        LinearSystem::new(a, b)
+    }
+
+    /// all flows that keep every node balanced, exact. usually infinitely many: the free variables
+    /// are flows we can choose, e.g. to route traffic around a closed street
+    pub fn general_solution(&self) -> Solution<BigRational> {
+        self.conservation_system().solve()
+    }
+
+    /// the general solution written out, like "x1 = 600 - x5" for each basic flow and "x5 free"
+    pub fn describe(&self, solution: &Solution<BigRational>) -> String {
+        match solution {
+            Solution::Inconsistent => "no solution: the known flows can't be balanced".to_string(),
+            Solution::Unique(x) => self
+                .unknowns
+                .iter()
+                .zip(x.iter())
+                .map(|(name, value)| format!("{} = {}", name, value))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Solution::Infinite {
+                particular,
+                null_space_basis,
+            } => {
+                // the null space basis has one vector per free column, in the same order
+                let free = rref(&self.conservation_system().coefficients).free_columns;
+                self.unknowns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| match free.contains(&i) {
+                        true => format!("{} free", name),
+                        false => {
+                            let terms = free
+                                .iter()
+                                .zip(null_space_basis)
+                                .map(|(&f, v)| (v[i].clone(), self.unknowns[f].as_str()))
+                                .collect();
+                            format!("{} = {}", name, linear_expression(&particular[i], terms))
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+    }
+
+    /// the smallest and largest value of each unknown flow, with all nodes balanced,
+    /// every flow within its capacity and (if `non_negative`) no flow going against its edge.
+    /// Err if no flows satisfy all of that, listing the nodes and capacities that conflict
+    pub fn flow_ranges(&self, non_negative: bool) -> Result<Vec<FlowRange>, String> {
+        let n = self.unknowns.len();
+        // the simplex keeps its variables >= 0, so a flow that may go backwards is x = u - v,
+        // and its coefficients are repeated negated for the v's
+        let split = |coefficients: Vec<f64>| match non_negative {
+            true => coefficients,
+            false => coefficients
+                .iter()
+                .copied()
+                .chain(coefficients.iter().map(|c| -c))
+                .collect(),
+        };
+        let single = |j: usize, coefficient: f64| {
+            let mut coefficients = vec![0.0; n];
+            coefficients[j] = coefficient;
+            split(coefficients)
+        };
+
+        let system = self.conservation_system();
+        let mut constraints = vec![];
+        let mut descriptions = vec![];
+        for (row, node) in self.inner_nodes().into_iter().enumerate() {
+            let coefficients: Vec<f64> = system
+                .coefficients
+                .row(row)
+                .iter()
+                .map(|a| a.to_f64())
+                .collect();
+            constraints.push(Constraint::new(
+                split(coefficients),
+                Relation::Equal,
+                system.constants[row].to_f64(),
+            ));
+            descriptions.push(format!("node {}", self.nodes[node]));
+        }
+        for edge in &self.edges {
+            if let (Flow::Unknown(j), Some(capacity)) = (&edge.flow, &edge.capacity) {
+                constraints.push(Constraint::new(
+                    single(*j, 1.0),
+                    Relation::LessOrEqual,
+                    capacity.to_f64(),
+                ));
+                descriptions.push(format!("{} <= {}", self.unknowns[*j], capacity));
+            }
+        }
+
+        // minimizing x and -x for each flow
+        let optimum = |objective: Vec<f64>| match LinearProgram::new(objective, constraints.clone())
+            .solve()
+        {
+            LpSolution::Optimal { value, .. } => Ok(Some(value)),
+            LpSolution::Unbounded => Ok(None),
+            LpSolution::Infeasible { conflicting } => Err(format!(
+                "no flows within the bounds, conflicting: {}",
+                conflicting
+                    .iter()
+                    .map(|&i| descriptions[i].as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        };
+        (0..n)
+            .map(|j| {
+                Ok(FlowRange {
+                    name: self.unknowns[j].clone(),
+                    min: optimum(single(j, 1.0))?,
+                    max: optimum(single(j, -1.0))?.map(|value| -value),
+                })
+            })
+            .collect()
+    }
+}
+
+fn index_or_push(names: &mut Vec<String>, name: &str) -> usize {
+    match names.iter().position(|n| n == name) {
+        Some(i) => i,
+        None => {
+            names.push(name.to_string());
+            names.len() - 1
+        }
+    }
+}
+
+/// e.g. "600 - x5 + 1/2 x6", leaving out zero terms and coefficients of 1
+fn linear_expression(constant: &BigRational, terms: Vec<(BigRational, &str)>) -> String {
+    let mut expression = match constant.is_zero() {
+        true => String::new(),
+        false => constant.to_string(),
+    };
+    for (coefficient, name) in terms.into_iter().filter(|(c, _)| !c.is_zero()) {
+        let sign = if coefficient.is_negative() { "-" } else { "+" };
+        let magnitude = coefficient.abs();
+        let term = match magnitude.is_one() {
+            true => name.to_string(),
+            false => format!("{} {}", magnitude, name),
+        };
+        expression = match (expression.is_empty(), sign) {
+            (true, "+") => term,
+            (true, _) => format!("-{}", term),
+            (false, _) => format!("{} {} {}", expression, sign, term),
+        };
+    }
+    if expression.is_empty() {
+        "0".to_string()
+    } else {
+        expression
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::field::rational;
+    use approx::assert_relative_eq;
+
+    // the traffic network from Lay's linear algebra (section 1.6): intersections A, B, C, D
+    const LAY_NETWORK: &str = "
+* -> A 300
+* -> A 500
+A -> D x1
+A -> B x2
+B -> * x3
+C -> B x4
+C -> D x5
+B -> * 300
+* -> C 100
+* -> C 400
+D -> * 600
+";
+
+    #[test]
+    fn finds_general_solution() {
+        let network = parse_flow_network(LAY_NETWORK).unwrap();
+
+        let solution = network.general_solution();
+
+        let Solution::Infinite {
+            particular,
+            null_space_basis,
+        } = &solution
+        else {
+            panic!("expected free variables");
+        };
+        assert_eq!(null_space_basis.len(), 1);
+        assert_eq!(particular[0], rational(600, 1));
+        assert_eq!(
+            network.describe(&solution),
+            "x1 = 600 - x5\nx2 = 200 + x5\nx3 = 400\nx4 = 500 - x5\nx5 free"
+        );
+    }
+
+    #[test]
+    fn builds_one_equation_per_node() {
+        let network = parse_flow_network(LAY_NETWORK).unwrap();
+
+        let system = network.conservation_system();
+
+        // the outside has no equation
+        assert_eq!(network.nodes.len(), 5);
+        assert_eq!(system.coefficients.nrows(), 4);
+        // A: x1 + x2 = 800
+        assert_eq!(
+            system
+                .coefficients
+                .row(0)
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>(),
+            [-1, -1, 0, 0, 0].map(|v| rational(v, 1))
+        );
+        assert_eq!(system.constants[0], rational(-800, 1));
+    }
+
+    #[test]
+    fn finds_flow_ranges_within_bounds() {
+        let network = parse_flow_network(LAY_NETWORK).unwrap();
+
+        // x5 can be anything in 0..500 without making another flow negative
+        let ranges = network.flow_ranges(true).unwrap();
+        assert_eq!(ranges[4].name, "x5");
+        assert_relative_eq!(ranges[4].min.unwrap(), 0.0, epsilon = 1e-9);
+        assert_relative_eq!(ranges[4].max.unwrap(), 500.0, epsilon = 1e-9);
+        assert_relative_eq!(ranges[0].min.unwrap(), 100.0, epsilon = 1e-9);
+
+        // without the non-negativity, nothing limits x5
+        let ranges = network.flow_ranges(false).unwrap();
+        assert_eq!((ranges[4].min, ranges[4].max), (None, None));
+        assert_relative_eq!(ranges[2].min.unwrap(), 400.0, epsilon = 1e-9);
+
+        // a capacity on x2 = 200 + x5 limits x5 to 400
+        let limited =
+            parse_flow_network(&LAY_NETWORK.replace("A -> B x2", "A -> B x2 <= 600")).unwrap();
+        let ranges = limited.flow_ranges(true).unwrap();
+        assert_relative_eq!(ranges[4].max.unwrap(), 400.0, epsilon = 1e-9);
+        assert_relative_eq!(ranges[3].min.unwrap(), 100.0, epsilon = 1e-9);
+
+        let impossible =
+            parse_flow_network(&LAY_NETWORK.replace("A -> B x2", "A -> B x2 <= 100")).unwrap();
+        assert!(impossible
+            .flow_ranges(true)
+            .unwrap_err()
+            .contains("x2 <= 100"));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(
+            parse_flow_network("A -> B x1\nA B 3"),
+            Err("line 2: expected 'from -> to flow', got: A B 3".to_string())
+        );
+        assert!(parse_flow_network("A -> B 3 <= 5")
+            .unwrap_err()
+            .contains("only unknown flows"));
+        assert!(parse_flow_network("A -> B #1").is_err());
+        // plain numbers, no SPICE suffixes like in netlists
+        assert_eq!(
+            parse_flow_network("A -> B 1m"),
+            Err("line 1: invalid flow '1m'".to_string())
+        );
+        assert_eq!(
+            parse_flow_network("A -> B x1 <= 2k"),
+            Err("line 1: invalid number '2k'".to_string())
+        );
+        assert_eq!(
+            parse_flow_network("A -> B 2.5").unwrap().edges[0].flow,
+            Flow::Known(rational(5, 2))
+        );
+
+        // 5 in, 3 out
+        let network = parse_flow_network("* -> A 5\nA -> * 3").unwrap();
+        assert_eq!(network.general_solution(), Solution::Inconsistent);
+    }
+}
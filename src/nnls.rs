@@ -0,0 +1,101 @@
+use crate::field::F64_TOLERANCE;
+use nalgebra::{DMatrix, DVector};
+
+/// non-negative least squares: the x >= 0 minimizing |Ax - b| (Lawson-Hanson active set method)
+///
+/// variables are either held at 0 (active) or free (passive). we keep freeing the variable that would reduce
+/// the residual the most, solve the unconstrained least squares problem for the free ones,
+/// and step back towards the previous x where that made some of them negative
+pub fn nnls(a: &DMatrix<f64>, b: &DVector<f64>) -> DVector<f64> {
+    let n = a.ncols();
+    let mut x = DVector::zeros(n);
+    let mut passive = vec![false; n];
+
+    // each outer iteration frees a variable, the inner loop can only fix them again, so this is plenty
+    for _ in 0..3 * n.max(1) {
+        // negative gradient of |Ax - b|^2 / 2: where it's positive, increasing x_j reduces the residual
+        let w = a.transpose() * (b - a * &x);
+        let Some(j) = (0..n)
+            .filter(|&j| !passive[j] && w[j] > F64_TOLERANCE)
+            .max_by(|&i, &j| w[i].total_cmp(&w[j]))
+        else {
+            break;
+        };
+        passive[j] = true;
+
+        loop {
+            let s = least_squares_on(a, b, &passive);
+            if (0..n).all(|i| !passive[i] || s[i] > F64_TOLERANCE) {
+                x = s;
+                break;
+            }
+            // move from x towards s as far as possible while staying >= 0
+            let alpha = (0..n)
+                .filter(|&i| passive[i] && s[i] <= F64_TOLERANCE)
+                .map(|i| x[i] / (x[i] - s[i]))
+                .fold(f64::INFINITY, f64::min);
+            x += (s - &x) * alpha;
+            for i in 0..n {
+                if passive[i] && x[i] <= F64_TOLERANCE {
+                    passive[i] = false;
+                    x[i] = 0.0;
+                }
+            }
+        }
+    }
+    x
+}
+
+/// unconstrained least squares using only the passive columns, the others are 0
+fn least_squares_on(a: &DMatrix<f64>, b: &DVector<f64>, passive: &[bool]) -> DVector<f64> {
+    let columns: Vec<usize> = (0..a.ncols()).filter(|&j| passive[j]).collect();
+    let a_passive = a.select_columns(&columns);
+    let solution = a_passive
+        .svd(true, true)
+        .solve(b, F64_TOLERANCE)
+        .expect("svd computed with u and v_t");
+    let mut s = DVector::zeros(a.ncols());
+    for (i, &j) in columns.iter().enumerate() {
+        s[j] = solution[i];
+    }
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn solves_consistent_system_exactly() {
+        let a = DMatrix::from_row_slice(2, 2, &[1.0, 1.0, 1.0, -1.0]);
+        let b = DVector::from_row_slice(&[3.0, 1.0]);
+
+        assert_relative_eq!(
+            nnls(&a, &b),
+            DVector::from_row_slice(&[2.0, 1.0]),
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn keeps_variables_non_negative() {
+        // the unconstrained least squares solution is (-1, 2) (exact), here x1 has to stay 0
+        #[rustfmt::skip]
+        let a = DMatrix::from_row_slice(3, 2, &[
+            1.0, 0.0,
+            0.0, 1.0,
+            1.0, 1.0,
+        ]);
+        let b = DVector::from_row_slice(&[-1.0, 2.0, 1.0]);
+
+        let x = nnls(&a, &b);
+
+        // with x1 = 0: minimize (x2 - 2)^2 + (x2 - 1)^2 -> x2 = 1.5
+        assert_relative_eq!(x, DVector::from_row_slice(&[0.0, 1.5]), epsilon = 1e-9);
+        // optimality (KKT): the gradient doesn't point into the allowed region
+        let w = a.transpose() * (b - &a * &x);
+        assert!(w[0] <= 1e-9);
+        assert_relative_eq!(w[1], 0.0, epsilon = 1e-9);
+    }
+}
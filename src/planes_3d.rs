@@ -0,0 +1,102 @@
+use crate::functions::{draw_plane3d_general_form, to_world_3d};
+use bevy::prelude::*;
+use linear_alg::linear_system::{LinearSystem, Solution};
+use nalgebra::{DMatrix, DVector};
+
+/// draws the planes of a 3x3 system (one per equation) and highlights its solution set:
+/// a point, a line, or a plane (when all planes are the same). also draws the column space vectors
+pub fn add_planes_3d_system(app: &mut App) {
+    app.add_systems(Startup, setup_planes)
+        .add_systems(Update, (draw_planes, draw_column_space_3d));
+}
+
+#[derive(Resource)]
+struct Planes {
+    /// [A b], one row a b c | d per plane
+    augmented: DMatrix<f64>,
+    solution: Solution<f64>,
+}
+
+fn setup_planes(mut commands: Commands) {
+    // from Lay's linear algebra (section 1.1): x - 2y + z = 0, 2y - 8z = 8, 5x - 5z = 10
+    #[rustfmt::skip]
+    let augmented = DMatrix::from_row_slice(3, 4, &[
+        1.0, -2.0, 1.0, 0.0,
+        0.0, 2.0, -8.0, 8.0,
+        5.0, 0.0, -5.0, 10.0,
+    ]);
+    let system = LinearSystem::new(
+        augmented.columns(0, 3).into_owned(),
+        augmented.column(3).into_owned(),
+    );
+    commands.insert_resource(Planes {
+        augmented,
+        solution: system.solve(),
+    });
+}
+
+fn draw_planes(mut gizmos: Gizmos, planes: Res<Planes>) {
+    let half_size = 5.0;
+    let colors = [Color::ORANGE, Color::CYAN, Color::PINK];
+    for (row, color) in planes.augmented.row_iter().zip(colors.iter().cycle()) {
+        draw_plane3d_general_form(
+            &mut gizmos,
+            row[0] as f32,
+            row[1] as f32,
+            row[2] as f32,
+            row[3] as f32,
+            half_size,
+            10,
+            *color,
+        );
+    }
+
+    let world = |v: &DVector<f64>| to_world_3d(v[0] as f32, v[1] as f32, v[2] as f32);
+    match &planes.solution {
+        Solution::Unique(x) => {
+            gizmos.sphere(world(x), Quat::IDENTITY, 0.2, Color::WHITE);
+        }
+        Solution::Infinite {
+            particular,
+            null_space_basis,
+        } => match &null_space_basis[..] {
+            // particular + t v
+            [v] => {
+                let p = world(particular);
+                let direction = world(v).normalize() * half_size * 1.5;
+                gizmos.line(p - direction, p + direction, Color::WHITE);
+            }
+            // all planes are the same: that's the solution set, so draw it on top
+            [_, _] => {
+                let row = planes
+                    .augmented
+                    .row_iter()
+                    .find(|row| row.columns(0, 3).iter().any(|a| *a != 0.0))
+                    .expect("a non-zero row, since the rank is 1");
+                draw_plane3d_general_form(
+                    &mut gizmos,
+                    row[0] as f32,
+                    row[1] as f32,
+                    row[2] as f32,
+                    row[3] as f32,
+                    half_size,
+                    20,
+                    Color::WHITE,
+                );
+            }
+            // A = 0 and b = 0: every point is a solution, nothing to highlight
+            _ => {}
+        },
+        // no point is on all planes
+        Solution::Inconsistent => {}
+    }
+}
+
+/// the columns of A and b as arrows from the origin: b is a combination of the columns exactly when there's a solution
+fn draw_column_space_3d(mut gizmos: Gizmos, planes: Res<Planes>) {
+    for (c, column) in planes.augmented.column_iter().enumerate() {
+        let end = to_world_3d(column[0] as f32, column[1] as f32, column[2] as f32);
+        let color = if c == 3 { Color::YELLOW } else { Color::BLUE };
+        gizmos.arrow(Vec3::ZERO, end, color);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::field::Field;
+use crate::row_reduction::{column_scales, find_pivot_row};
+use nalgebra::{DMatrix, DVector};
+
+/// PA = LU, with P a permutation matrix, L unit lower triangular and U in echelon form
+///
+/// unlike the libraries' lu, we keep P around and apply it to b when solving,
+/// so solving Ly = Pb and Ux = y "by hand" gives the same x as solving Ax = b directly
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plu<T: Field> {
+    pub p: DMatrix<T>,
+    pub l: DMatrix<T>,
+    pub u: DMatrix<T>,
+    /// row i of PA is row permutation[i] of A
+    pub permutation: Vec<usize>,
+    /// columns of U with a pivot
+    pub pivot_columns: Vec<usize>,
+    /// whether we made an odd number of row swaps, which flips the sign of the determinant
+    odd_swaps: bool,
+}
+
+/// factorizes any (also rectangular or singular) matrix. columns without pivot are skipped, like in row reduction
+pub fn plu<T: Field>(a: &DMatrix<T>) -> Plu<T> {
+    let (rows, cols) = a.shape();
+    let mut u = a.clone();
+    let mut l = DMatrix::from_fn(rows, rows, |r, c| if r == c { T::one() } else { T::zero() });
+    let mut permutation: Vec<usize> = (0..rows).collect();
+    let mut pivot_columns = vec![];
+    let mut odd_swaps = false;
+    let scales = column_scales(a);
+
+    for col in 0..cols {
+        let pivot_row = pivot_columns.len();
+        let Some(best_row) = find_pivot_row(&u, pivot_row, col, scales[col]) else {
+            continue;
+        };
+
+        if best_row != pivot_row {
+            u.swap_rows(pivot_row, best_row);
+            permutation.swap(pivot_row, best_row);
+            // the multipliers found so far belong to the rows, so they move with them
+            for c in 0..pivot_row {
+                l.swap((pivot_row, c), (best_row, c));
+            }
+            odd_swaps = !odd_swaps;
+        }
+
+        let pivot = u[(pivot_row, col)].clone();
+        for r in pivot_row + 1..rows {
+            let factor = u[(r, col)].clone() / pivot.clone();
+            for c in col..cols {
+                u[(r, c)] = u[(r, c)].clone() - factor.clone() * u[(pivot_row, c)].clone();
+            }
+            // exactly 0, also for floats
+            u[(r, col)] = T::zero();
+            l[(r, pivot_row)] = factor;
+        }
+
+        pivot_columns.push(col);
+    }
+
+    let p = DMatrix::from_fn(rows, rows, |r, c| {
+        if permutation[r] == c {
+            T::one()
+        } else {
+            T::zero()
+        }
+    });
+
+    Plu {
+        p,
+        l,
+        u,
+        permutation,
+        pivot_columns,
+        odd_swaps,
+    }
+}
+
+impl<T: Field> Plu<T> {
+    pub fn rank(&self) -> usize {
+        self.pivot_columns.len()
+    }
+
+    /// product of U's diagonal, with the sign flipped for each row swap. None for non square matrices
+    pub fn determinant(&self) -> Option<T> {
+        let (rows, cols) = self.u.shape();
+        if rows != cols {
+            return None;
+        }
+        if self.rank() < cols {
+            return Some(T::zero());
+        }
+        let product = (0..rows).fold(T::one(), |acc, i| acc * self.u[(i, i)].clone());
+        Some(if self.odd_swaps { -product } else { product })
+    }
+
+    /// Pb, i.e. b with its entries in the order of the rows of PA
+    pub fn permute(&self, b: &DVector<T>) -> DVector<T> {
+        DVector::from_fn(b.len(), |i, _| b[self.permutation[i]].clone())
+    }
+
+    /// solves Ax = b via Ly = Pb and Ux = y. None if A is not square or singular
+    pub fn solve(&self, b: &DVector<T>) -> Option<DVector<T>> {
+        let (rows, cols) = self.u.shape();
+        if rows != cols || self.rank() < cols {
+            return None;
+        }
+        let y = forward_substitution(&self.l, &self.permute(b));
+        Some(back_substitution(&self.u, &y))
+    }
+}
+
+/// solves Ly = b for a lower triangular L with non-zero diagonal, top to bottom
+pub fn forward_substitution<T: Field>(l: &DMatrix<T>, b: &DVector<T>) -> DVector<T> {
+    let n = b.len();
+    let mut y = DVector::from_element(n, T::zero());
+    for i in 0..n {
+        let sum = (0..i).fold(T::zero(), |acc, j| acc + l[(i, j)].clone() * y[j].clone());
+        y[i] = (b[i].clone() - sum) / l[(i, i)].clone();
+    }
+    y
+}
+
+/// solves Ux = y for an upper triangular U with non-zero diagonal, bottom to top
+pub fn back_substitution<T: Field>(u: &DMatrix<T>, y: &DVector<T>) -> DVector<T> {
+    let n = y.len();
+    let mut x = DVector::from_element(n, T::zero());
+    for i in (0..n).rev() {
+        let sum = (i + 1..n).fold(T::zero(), |acc, j| acc + u[(i, j)].clone() * x[j].clone());
+        x[i] = (y[i].clone() - sum) / u[(i, i)].clone();
+    }
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::field::rational;
+    use approx::assert_relative_eq;
+    use num_rational::BigRational;
+
+    fn rational_matrix(rows: usize, cols: usize, values: &[i64]) -> DMatrix<BigRational> {
+        DMatrix::from_row_slice(rows, cols, values).map(|v| rational(v, 1))
+    }
+
+    #[test]
+    fn reproduces_a() {
+        #[rustfmt::skip]
+        let a = DMatrix::from_row_slice(3, 3, &[
+            2.0, 1.0, 3.0,
+            1.0, -1.0, 2.0,
+            3.0, 2.0, 1.0,
+        ]);
+
+        let plu = plu(&a);
+
+        // partial pivoting: the row with the largest entry in the first column (3) goes first
+        assert_eq!(plu.permutation, vec![2, 1, 0]);
+        assert_relative_eq!(&plu.p * &a, &plu.l * &plu.u, epsilon = 1e-12);
+        for r in 0..3 {
+            assert_eq!(plu.l[(r, r)], 1.0);
+            for c in r + 1..3 {
+                assert_eq!(plu.l[(r, c)], 0.0);
+                assert_eq!(plu.u[(c, r)], 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn solves_by_substitution() {
+        #[rustfmt::skip]
+        let a = DMatrix::from_row_slice(3, 3, &[
+            2.0, 1.0, 3.0,
+            1.0, -1.0, 2.0,
+            3.0, 2.0, 1.0,
+        ]);
+        let b = DVector::from_row_slice(&[5.0, 4.0, 7.0]);
+
+        let x = plu(&a).solve(&b).unwrap();
+
+        assert_relative_eq!(
+            x,
+            DVector::from_row_slice(&[3.0, -1.0, 0.0]),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn determinant_and_rank() {
+        #[rustfmt::skip]
+        let a = rational_matrix(3, 3, &[
+            0, 1, 4,
+            1, 3, 5,
+            3, 7, 7,
+        ]);
+        let singular = plu(&a);
+        assert_eq!(singular.rank(), 2);
+        assert_eq!(singular.determinant(), Some(rational(0, 1)));
+        assert_eq!(singular.p * a, singular.l * singular.u);
+
+        // needs a swap, so the sign of the determinant flips
+        #[rustfmt::skip]
+        let b = rational_matrix(3, 3, &[
+            0, 1, 2,
+            1, 0, 3,
+            4, -3, 8,
+        ]);
+        let invertible = plu(&b);
+        assert_eq!(invertible.rank(), 3);
+        assert_eq!(invertible.determinant(), Some(rational(-2, 1)));
+        assert_eq!(invertible.p * b, invertible.l * invertible.u);
+    }
+
+    #[test]
+    fn rectangular() {
+        #[rustfmt::skip]
+        let a = rational_matrix(2, 3, &[
+            1, 2, 3,
+            2, 4, 7,
+        ]);
+
+        let plu = plu(&a);
+
+        assert_eq!(plu.pivot_columns, vec![0, 2]);
+        assert_eq!(plu.determinant(), None);
+        assert_eq!(plu.p * a, plu.l * plu.u);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::field::Field;
+use nalgebra::DMatrix;
+use std::fmt::{self, Display, Formatter};
+
+/// reduced row echelon form of a matrix, together with what we usually want to read from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rref<T: Field> {
+    pub matrix: DMatrix<T>,
+    /// columns with a leading 1, in order. row i of the reduced matrix has its leading 1 in pivot_columns[i]
+    pub pivot_columns: Vec<usize>,
+    /// the remaining columns. for a coefficient matrix these are the free variables
+    pub free_columns: Vec<usize>,
+}
+
+impl<T: Field> Rref<T> {
+    pub fn rank(&self) -> usize {
+        self.pivot_columns.len()
+    }
+}
+
+/// reduces a matrix to reduced row echelon form (Gauss-Jordan elimination)
+///
+/// works the same for exact (`BigRational`) and approximate (`f64`) scalars,
+/// e.g. with rationals we get 1/4 and 5/4 instead of 0.2500000001 and 1.2499999999
+pub fn rref<T: Field>(matrix: &DMatrix<T>) -> Rref<T> {
+    reduce(matrix, false, |_, _| {})
+}
+
+/// like `rref`, for an augmented matrix [A b]: the last column isn't cleaned up at the end,
+/// since (unless it has a pivot) it ends up holding the solution, which can be tiny without being 0 (e.g. pA currents)
+pub fn rref_augmented<T: Field>(matrix: &DMatrix<T>) -> Rref<T> {
+    reduce(matrix, true, |_, _| {})
+}
+
+/// like `rref`, but also records each elementary row operation and the matrix after it
+pub fn row_reduce_traced<T: Field>(matrix: &DMatrix<T>) -> RowReductionTrace<T> {
+    let mut steps = vec![];
+    let result = reduce(matrix, false, |op, m| {
+        steps.push(RowReductionStep {
+            op: op.clone(),
+            matrix: m.clone(),
+        })
+    });
+    RowReductionTrace {
+        initial: matrix.clone(),
+        steps,
+        result,
+    }
+}
+
+/// elementary row operations. rows are 0 based here, but displayed 1 based (R1, R2, ..) like in textbooks
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowOp<T: Field> {
+    /// R_a <-> R_b
+    Swap { a: usize, b: usize },
+    /// R_row <- factor * R_row
+    Scale { row: usize, factor: T },
+    /// R_row <- R_row + factor * R_source
+    Replace {
+        row: usize,
+        source: usize,
+        factor: T,
+    },
+}
+
+impl<T: Field> RowOp<T> {
+    pub fn apply(&self, m: &mut DMatrix<T>) {
+        match self {
+            RowOp::Swap { a, b } => m.swap_rows(*a, *b),
+            RowOp::Scale { row, factor } => {
+                for c in 0..m.ncols() {
+                    m[(*row, c)] = factor.clone() * m[(*row, c)].clone();
+                }
+            }
+            RowOp::Replace {
+                row,
+                source,
+                factor,
+            } => {
+                for c in 0..m.ncols() {
+                    m[(*row, c)] = m[(*row, c)].clone() + factor.clone() * m[(*source, c)].clone();
+                }
+            }
+        }
+    }
+}
+
+impl<T: Field> Display for RowOp<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RowOp::Swap { a, b } => write!(f, "R{} <-> R{}", a + 1, b + 1),
+            RowOp::Scale { row, factor } => write!(f, "R{} <- ({}) R{}", row + 1, factor, row + 1),
+            RowOp::Replace {
+                row,
+                source,
+                factor,
+            } => write!(
+                f,
+                "R{} <- R{} + ({}) R{}",
+                row + 1,
+                row + 1,
+                factor,
+                source + 1
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowReductionStep<T: Field> {
+    pub op: RowOp<T>,
+    /// the matrix after applying `op`
+    pub matrix: DMatrix<T>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowReductionTrace<T: Field> {
+    pub initial: DMatrix<T>,
+    pub steps: Vec<RowReductionStep<T>>,
+    /// note that for floats, the result has negligible values set to 0, so it can differ slightly from the last step
+    pub result: Rref<T>,
+}
+
+impl<T: Field> RowReductionTrace<T> {
+    pub fn ops(&self) -> Vec<RowOp<T>> {
+        self.steps.iter().map(|step| step.op.clone()).collect()
+    }
+
+    /// the matrix after `step` operations, where 0 is the initial matrix
+    pub fn matrix_at(&self, step: usize) -> &DMatrix<T> {
+        match step {
+            0 => &self.initial,
+            _ => &self.steps[step - 1].matrix,
+        }
+    }
+
+    /// applies the recorded operations to the initial matrix again,
+    /// checking that every intermediate matrix is what was recorded
+    pub fn replay(&self) -> Result<DMatrix<T>, String> {
+        let mut m = self.initial.clone();
+        for (i, step) in self.steps.iter().enumerate() {
+            step.op.apply(&mut m);
+            if m != step.matrix {
+                return Err(format!(
+                    "step {} ({}) produced:{}but the trace recorded:{}",
+                    i + 1,
+                    step.op,
+                    m,
+                    step.matrix
+                ));
+            }
+        }
+        Ok(m)
+    }
+}
+
+impl<T: Field> Display for RowReductionTrace<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "initial:{}", self.initial)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            write!(f, "{}. {}{}", i + 1, step.op, step.matrix)?;
+        }
+        Ok(())
+    }
+}
+
+/// the elimination itself, reporting each row operation (with the matrix after it) to `on_step`
+fn reduce<T, F>(matrix: &DMatrix<T>, augmented: bool, mut on_step: F) -> Rref<T>
+where
+    T: Field,
+    F: FnMut(&RowOp<T>, &DMatrix<T>),
+{
+    let mut m = matrix.clone();
+    let (rows, cols) = m.shape();
+    let scales = column_scales(matrix);
+
+    let mut pivot_columns = vec![];
+    let mut free_columns = vec![];
+
+    let mut apply = |op: RowOp<T>, m: &mut DMatrix<T>| {
+        op.apply(m);
+        on_step(&op, m);
+    };
+
+    for col in 0..cols {
+        let pivot_row = pivot_columns.len();
+        let Some(best_row) = find_pivot_row(&m, pivot_row, col, scales[col]) else {
+            free_columns.push(col);
+            continue;
+        };
+
+        if best_row != pivot_row {
+            apply(
+                RowOp::Swap {
+                    a: pivot_row,
+                    b: best_row,
+                },
+                &mut m,
+            );
+        }
+
+        // scale so the pivot becomes 1
+        let pivot = m[(pivot_row, col)].clone();
+        if !pivot.is_one() {
+            apply(
+                RowOp::Scale {
+                    row: pivot_row,
+                    factor: T::one() / pivot,
+                },
+                &mut m,
+            );
+        }
+
+        // eliminate the column above and below the pivot
+        for r in 0..rows {
+            let factor = m[(r, col)].clone();
+            if r == pivot_row || factor.is_zero() {
+                continue;
+            }
+            apply(
+                RowOp::Replace {
+                    row: r,
+                    source: pivot_row,
+                    factor: -factor,
+                },
+                &mut m,
+            );
+        }
+
+        pivot_columns.push(col);
+    }
+
+    // floats leave residue like 1e-17 where there should be 0, and 0.9999999999999999 for leading 1s
+    let cleaned_columns = if augmented {
+        cols.saturating_sub(1)
+    } else {
+        cols
+    };
+    for (col, &scale) in scales.iter().enumerate().take(cleaned_columns) {
+        for value in m.column_mut(col).iter_mut() {
+            if value.is_negligible_in(scale) {
+                *value = T::zero();
+            }
+        }
+    }
+    for (row, &col) in pivot_columns.iter().enumerate() {
+        for r in 0..rows {
+            m[(r, col)] = if r == row { T::one() } else { T::zero() };
+        }
+    }
+
+    Rref {
+        matrix: m,
+        pivot_columns,
+        free_columns,
+    }
+}
+
+/// the largest magnitude in each column, what `Field::is_negligible_in` compares with
+pub fn column_scales<T: Field>(m: &DMatrix<T>) -> Vec<f64> {
+    m.column_iter()
+        .map(|column| column.iter().map(|v| v.magnitude()).fold(0.0, f64::max))
+        .collect()
+}
+
+/// row (starting at `from_row`) with the largest entry in `col`, None if all are (approximately) 0
+/// `scale`: the largest magnitude in the column of the original matrix
+pub fn find_pivot_row<T: Field>(
+    m: &DMatrix<T>,
+    from_row: usize,
+    col: usize,
+    scale: f64,
+) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    for r in from_row..m.nrows() {
+        let value = &m[(r, col)];
+        if value.is_negligible_in(scale) {
+            continue;
+        }
+        match best {
+            Some(b) if m[(b, col)].magnitude() >= value.magnitude() => {}
+            _ => best = Some(r),
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::field::rational;
+    use approx::assert_relative_eq;
+    use num_rational::BigRational;
+
+    fn rational_matrix(rows: usize, cols: usize, values: &[i64]) -> DMatrix<BigRational> {
+        DMatrix::from_row_slice(rows, cols, values).map(|v| rational(v, 1))
+    }
+
+    #[test]
+    fn reduces_to_exact_fractions() {
+        #[rustfmt::skip]
+        let a = rational_matrix(3, 5, &[
+            3, 0, 1, 0, 0,
+            8, 0, 0, 2, 0,
+            0, 2, 2, 1, 0,
+        ]);
+
+        let reduced = rref(&a);
+
+        #[rustfmt::skip]
+        let expected = DMatrix::from_row_slice(3, 5, &[
+            rational(1, 1), rational(0, 1), rational(0, 1), rational(1, 4), rational(0, 1),
+            rational(0, 1), rational(1, 1), rational(0, 1), rational(5, 4), rational(0, 1),
+            rational(0, 1), rational(0, 1), rational(1, 1), rational(-3, 4), rational(0, 1),
+        ]);
+        assert_eq!(reduced.matrix, expected);
+        assert_eq!(reduced.pivot_columns, vec![0, 1, 2]);
+        assert_eq!(reduced.free_columns, vec![3, 4]);
+        assert_eq!(reduced.rank(), 3);
+    }
+
+    #[test]
+    fn reduces_with_zero_rows() {
+        #[rustfmt::skip]
+        let a = rational_matrix(3, 3, &[
+            1, 2, 3,
+            2, 4, 6,
+            1, 1, 1,
+        ]);
+
+        let reduced = rref(&a);
+
+        #[rustfmt::skip]
+        let expected = rational_matrix(3, 3, &[
+            1, 0, -1,
+            0, 1, 2,
+            0, 0, 0,
+        ]);
+        assert_eq!(reduced.matrix, expected);
+        assert_eq!(reduced.pivot_columns, vec![0, 1]);
+        assert_eq!(reduced.free_columns, vec![2]);
+    }
+
+    #[test]
+    fn reduces_floats() {
+        #[rustfmt::skip]
+        let a = DMatrix::from_row_slice(3, 4, &[
+            1.0, 1.0, 1.0, 2.0,
+            2.0, -1.0, 2.0, 7.0,
+            -1.0, -2.0, 3.0, 7.0,
+        ]);
+
+        let reduced = rref(&a);
+
+        assert_eq!(reduced.pivot_columns, vec![0, 1, 2]);
+        assert_eq!(reduced.free_columns, vec![3]);
+        let solution = reduced.matrix.column(3);
+        assert_relative_eq!(solution[0], 1.0, epsilon = 1e-12);
+        assert_relative_eq!(solution[1], -1.0, epsilon = 1e-12);
+        assert_relative_eq!(solution[2], 2.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn traces_row_operations() {
+        #[rustfmt::skip]
+        let a = rational_matrix(2, 3, &[
+            0, 2, 4,
+            1, 1, 3,
+        ]);
+
+        let trace = row_reduce_traced(&a);
+
+        assert_eq!(
+            trace.ops(),
+            vec![
+                RowOp::Swap { a: 0, b: 1 },
+                RowOp::Scale {
+                    row: 1,
+                    factor: rational(1, 2)
+                },
+                RowOp::Replace {
+                    row: 0,
+                    source: 1,
+                    factor: rational(-1, 1)
+                },
+            ]
+        );
+        #[rustfmt::skip]
+        let expected = rational_matrix(2, 3, &[
+            1, 0, 1,
+            0, 1, 2,
+        ]);
+        assert_eq!(trace.matrix_at(0), &a);
+        assert_eq!(trace.matrix_at(3), &expected);
+        assert_eq!(trace.result.matrix, expected);
+        assert_eq!(trace.result, rref(&a));
+        assert_eq!(trace.replay(), Ok(expected));
+
+        let text = trace.to_string();
+        assert!(text.contains("1. R1 <-> R2"));
+        assert!(text.contains("2. R2 <- (1/2) R2"));
+        assert!(text.contains("3. R1 <- R1 + (-1) R2"));
+    }
+
+    #[test]
+    fn replay_detects_wrong_step() {
+        let a = rational_matrix(2, 2, &[2, 4, 1, 3]);
+        let mut trace = row_reduce_traced(&a);
+        trace.steps[0].op = RowOp::Scale {
+            row: 0,
+            factor: rational(1, 3),
+        };
+
+        assert!(trace.replay().is_err());
+    }
+}
@@ -0,0 +1,378 @@
+use crate::field::F64_TOLERANCE;
+use nalgebra::DMatrix;
+
+/// minimize objective · x subject to the constraints and x >= 0
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearProgram {
+    pub objective: Vec<f64>,
+    pub constraints: Vec<Constraint>,
+}
+
+/// coefficients · x (relation) bound
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint {
+    pub coefficients: Vec<f64>,
+    pub relation: Relation,
+    pub bound: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    LessOrEqual,
+    GreaterOrEqual,
+    Equal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LpSolution {
+    Optimal {
+        x: Vec<f64>,
+        value: f64,
+    },
+    /// indices of constraints that can't hold together (with x >= 0).
+    /// these are the ones with a non-zero multiplier in the combination that proves it (Farkas' lemma),
+    /// so it's a conflicting subset, not necessarily the smallest one
+    Infeasible {
+        conflicting: Vec<usize>,
+    },
+    /// the objective can decrease without limit
+    Unbounded,
+}
+
+impl Constraint {
+    pub fn new(coefficients: Vec<f64>, relation: Relation, bound: f64) -> Constraint {
+        Constraint {
+            coefficients,
+            relation,
+            bound,
+        }
+    }
+}
+
+impl LinearProgram {
+    pub fn new(objective: Vec<f64>, constraints: Vec<Constraint>) -> LinearProgram {
+        for constraint in &constraints {
+            assert_eq!(
+                constraint.coefficients.len(),
+                objective.len(),
+                "need one coefficient per variable"
+            );
+        }
+        LinearProgram {
+            objective,
+            constraints,
+        }
+    }
+
+    /// two phase simplex: phase 1 finds a feasible basis by minimizing the sum of artificial variables,
+    /// phase 2 minimizes the objective from there
+    pub fn solve(&self) -> LpSolution {
+        let mut tableau = Tableau::new(self);
+
+        let phase1_costs = tableau.phase1_costs();
+        tableau.set_objective(&phase1_costs);
+        // with all costs >= 0, phase 1 can't be unbounded
+        tableau.optimize(tableau.columns());
+        if tableau.value() > F64_TOLERANCE {
+            return LpSolution::Infeasible {
+                conflicting: tableau.conflicting_rows(&phase1_costs),
+            };
+        }
+
+        tableau.drive_out_artificials();
+        let mut costs = vec![0.0; tableau.columns()];
+        costs[..self.objective.len()].copy_from_slice(&self.objective);
+        tableau.set_objective(&costs);
+        // artificial variables must stay out now
+        if !tableau.optimize(tableau.first_artificial) {
+            return LpSolution::Unbounded;
+        }
+
+        let x = tableau.values(self.objective.len());
+        let value = x.iter().zip(&self.objective).map(|(x, c)| x * c).sum();
+        LpSolution::Optimal { x, value }
+    }
+}
+
+/// rows are the constraints, then the objective (reduced costs), the last column is the right hand side
+/// columns: the original variables, a slack / surplus per inequality, an artificial per >= and = constraint
+struct Tableau {
+    m: DMatrix<f64>,
+    /// basic variable (column) of each constraint row
+    basis: Vec<usize>,
+    /// column that was the unit vector of each constraint row at the start,
+    /// which lets us read the row's multiplier (dual value) from the final objective row
+    unit_columns: Vec<usize>,
+    first_artificial: usize,
+}
+
+impl Tableau {
+    fn new(lp: &LinearProgram) -> Tableau {
+        let rows = lp.constraints.len();
+        let variables = lp.objective.len();
+        // right hand sides have to be >= 0, so negate the rows where they aren't (which flips <= and >=)
+        let constraints: Vec<Constraint> = lp
+            .constraints
+            .iter()
+            .map(|c| match c.bound < 0.0 {
+                true => Constraint {
+                    coefficients: c.coefficients.iter().map(|a| -a).collect(),
+                    relation: match c.relation {
+                        Relation::LessOrEqual => Relation::GreaterOrEqual,
+                        Relation::GreaterOrEqual => Relation::LessOrEqual,
+                        Relation::Equal => Relation::Equal,
+                    },
+                    bound: -c.bound,
+                },
+                false => c.clone(),
+            })
+            .collect();
+
+        let slacks = constraints
+            .iter()
+            .filter(|c| c.relation != Relation::Equal)
+            .count();
+        let artificials = constraints
+            .iter()
+            .filter(|c| c.relation != Relation::LessOrEqual)
+            .count();
+        let first_artificial = variables + slacks;
+        let columns = first_artificial + artificials;
+
+        let mut m = DMatrix::zeros(rows + 1, columns + 1);
+        let mut basis = vec![];
+        let (mut slack, mut artificial) = (variables, first_artificial);
+        for (r, constraint) in constraints.iter().enumerate() {
+            for (c, a) in constraint.coefficients.iter().enumerate() {
+                m[(r, c)] = *a;
+            }
+            m[(r, columns)] = constraint.bound;
+            match constraint.relation {
+                Relation::LessOrEqual => {
+                    m[(r, slack)] = 1.0;
+                    basis.push(slack);
+                    slack += 1;
+                }
+                Relation::GreaterOrEqual => {
+                    m[(r, slack)] = -1.0;
+                    m[(r, artificial)] = 1.0;
+                    basis.push(artificial);
+                    slack += 1;
+                    artificial += 1;
+                }
+                Relation::Equal => {
+                    m[(r, artificial)] = 1.0;
+                    basis.push(artificial);
+                    artificial += 1;
+                }
+            }
+        }
+
+        Tableau {
+            m,
+            unit_columns: basis.clone(),
+            basis,
+            first_artificial,
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.basis.len()
+    }
+
+    fn columns(&self) -> usize {
+        self.m.ncols() - 1
+    }
+
+    fn phase1_costs(&self) -> Vec<f64> {
+        (0..self.columns())
+            .map(|c| if c >= self.first_artificial { 1.0 } else { 0.0 })
+            .collect()
+    }
+
+    /// objective row = costs - (costs of the basic variables) * constraint rows,
+    /// so the basic variables have reduced cost 0 and the right hand side is -(objective value)
+    fn set_objective(&mut self, costs: &[f64]) {
+        let (rows, columns) = (self.rows(), self.columns());
+        for c in 0..=columns {
+            let cost = costs.get(c).copied().unwrap_or(0.0);
+            let basic: f64 = (0..rows)
+                .map(|r| costs[self.basis[r]] * self.m[(r, c)])
+                .sum();
+            self.m[(rows, c)] = cost - basic;
+        }
+    }
+
+    fn value(&self) -> f64 {
+        -self.m[(self.rows(), self.columns())]
+    }
+
+    /// pivots until no column before `allowed_columns` improves the objective. false if unbounded
+    fn optimize(&mut self, allowed_columns: usize) -> bool {
+        let (rows, columns) = (self.rows(), self.columns());
+        loop {
+            // Bland's rule (lowest index, for entering and leaving), so degenerate pivots can't cycle
+            let Some(entering) = (0..allowed_columns).find(|&c| self.m[(rows, c)] < -F64_TOLERANCE)
+            else {
+                return true;
+            };
+            let mut leaving: Option<usize> = None;
+            for r in 0..rows {
+                let a = self.m[(r, entering)];
+                if a <= F64_TOLERANCE {
+                    continue;
+                }
+                let ratio = self.m[(r, columns)] / a;
+                match leaving {
+                    Some(l) => {
+                        let best = self.m[(l, columns)] / self.m[(l, entering)];
+                        if ratio < best - F64_TOLERANCE
+                            || (ratio < best + F64_TOLERANCE && self.basis[r] < self.basis[l])
+                        {
+                            leaving = Some(r);
+                        }
+                    }
+                    None => leaving = Some(r),
+                }
+            }
+            let Some(leaving) = leaving else {
+                return false;
+            };
+            self.pivot(leaving, entering);
+        }
+    }
+
+    fn pivot(&mut self, row: usize, col: usize) {
+        let pivot = self.m[(row, col)];
+        for c in 0..self.m.ncols() {
+            self.m[(row, c)] /= pivot;
+        }
+        for r in 0..self.m.nrows() {
+            let factor = self.m[(r, col)];
+            if r == row || factor == 0.0 {
+                continue;
+            }
+            for c in 0..self.m.ncols() {
+                self.m[(r, c)] -= factor * self.m[(row, c)];
+            }
+        }
+        self.basis[row] = col;
+    }
+
+    /// artificial variables still in the basis (at 0) are swapped for a regular one where possible.
+    /// where not, the constraint was redundant and the artificial stays at 0
+    fn drive_out_artificials(&mut self) {
+        for r in 0..self.rows() {
+            if self.basis[r] < self.first_artificial {
+                continue;
+            }
+            if let Some(c) =
+                (0..self.first_artificial).find(|&c| self.m[(r, c)].abs() > F64_TOLERANCE)
+            {
+                self.pivot(r, c);
+            }
+        }
+    }
+
+    /// rows with a non-zero multiplier y in the final phase 1 objective row:
+    /// the reduced cost of a row's initial unit column is cost - y_row
+    fn conflicting_rows(&self, costs: &[f64]) -> Vec<usize> {
+        let rows = self.rows();
+        (0..rows)
+            .filter(|&r| {
+                let unit = self.unit_columns[r];
+                (costs[unit] - self.m[(rows, unit)]).abs() > F64_TOLERANCE
+            })
+            .collect()
+    }
+
+    /// values of the first `count` variables (0 for the non basic ones)
+    fn values(&self, count: usize) -> Vec<f64> {
+        let mut x = vec![0.0; count];
+        for (r, &b) in self.basis.iter().enumerate() {
+            if b < count {
+                x[b] = self.m[(r, self.columns())];
+            }
+        }
+        x
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use approx::assert_relative_eq;
+    use Relation::*;
+
+    #[test]
+    fn finds_optimum() {
+        // maximize 3x + 5y (minimize the negation) with x <= 4, 2y <= 12, 3x + 2y <= 18
+        let lp = LinearProgram::new(
+            vec![-3.0, -5.0],
+            vec![
+                Constraint::new(vec![1.0, 0.0], LessOrEqual, 4.0),
+                Constraint::new(vec![0.0, 2.0], LessOrEqual, 12.0),
+                Constraint::new(vec![3.0, 2.0], LessOrEqual, 18.0),
+            ],
+        );
+
+        let LpSolution::Optimal { x, value } = lp.solve() else {
+            panic!("expected an optimum");
+        };
+        assert_relative_eq!(x[0], 2.0, epsilon = 1e-9);
+        assert_relative_eq!(x[1], 6.0, epsilon = 1e-9);
+        assert_relative_eq!(value, -36.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn finds_optimum_with_greater_and_equal_constraints() {
+        // minimize x + y + z with x + y >= 2, y + z = 3, x - z <= -1 (negative bound: gets flipped)
+        let lp = LinearProgram::new(
+            vec![1.0, 1.0, 1.0],
+            vec![
+                Constraint::new(vec![1.0, 1.0, 0.0], GreaterOrEqual, 2.0),
+                Constraint::new(vec![0.0, 1.0, 1.0], Equal, 3.0),
+                Constraint::new(vec![1.0, 0.0, -1.0], LessOrEqual, -1.0),
+            ],
+        );
+
+        let LpSolution::Optimal { x, value } = lp.solve() else {
+            panic!("expected an optimum");
+        };
+        assert_relative_eq!(value, 3.0, epsilon = 1e-9);
+        assert!(x[0] + x[1] >= 2.0 - 1e-9);
+        assert_relative_eq!(x[1] + x[2], 3.0, epsilon = 1e-9);
+        assert!(x[0] - x[2] <= -1.0 + 1e-9);
+    }
+
+    #[test]
+    fn reports_conflicting_constraints() {
+        // x + y >= 5 and x + y <= 3 conflict, x <= 10 is unrelated
+        let lp = LinearProgram::new(
+            vec![1.0, 1.0],
+            vec![
+                Constraint::new(vec![1.0, 0.0], LessOrEqual, 10.0),
+                Constraint::new(vec![1.0, 1.0], GreaterOrEqual, 5.0),
+                Constraint::new(vec![1.0, 1.0], LessOrEqual, 3.0),
+            ],
+        );
+
+        assert_eq!(
+            lp.solve(),
+            LpSolution::Infeasible {
+                conflicting: vec![1, 2]
+            }
+        );
+    }
+
+    #[test]
+    fn detects_unbounded() {
+        // minimize -x with x - y <= 1: x and y can grow together
+        let lp = LinearProgram::new(
+            vec![-1.0, 0.0],
+            vec![Constraint::new(vec![1.0, -1.0], LessOrEqual, 1.0)],
+        );
+
+        assert_eq!(lp.solve(), LpSolution::Unbounded);
+    }
+}
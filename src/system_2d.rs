@@ -1,9 +1,50 @@
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::input::InputSystem;
 use bevy::prelude::*;
 
+/// pixels per world unit at the start, zoom changes it
+const INITIAL_PIXELS_PER_UNIT: f32 = 100.0;
+
+/// what the camera shows, in world coordinates (the same as the math: a line y = x goes through (1, 1)),
+/// so systems can fill the screen or draw things with a fixed size in pixels
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct WorldView {
+    pub visible: Rect,
+    pub units_per_pixel: f32,
+}
+
+impl WorldView {
+    /// a length of `pixels` on screen, in world units
+    pub fn pixels(&self, pixels: f32) -> f32 {
+        pixels * self.units_per_pixel
+    }
+
+    /// how far from the origin we can see. e.g. a line drawn this far in both directions from any of its points
+    /// near the origin crosses the whole screen
+    pub fn reach(&self) -> f32 {
+        self.visible.center().length() + self.visible.half_size().length()
+    }
+}
+
+impl Default for WorldView {
+    fn default() -> WorldView {
+        WorldView {
+            visible: Rect::from_center_size(Vec2::ZERO, Vec2::splat(10.0)),
+            units_per_pixel: 1.0 / INITIAL_PIXELS_PER_UNIT,
+        }
+    }
+}
+
+/// 2d space with a camera that pans (drag with the left mouse button) and zooms (scroll, around the cursor)
+/// the camera moves before `Update`, so systems drawing there see this frame's `WorldView`
 pub fn add_2d_space(app: &mut App) {
     app.add_plugins(DefaultPlugins)
+        .init_resource::<WorldView>()
         .add_systems(Startup, (setup_camera, setup_light))
-        .add_systems(Update, setup_axes);
+        .add_systems(
+            PreUpdate,
+            (pan_and_zoom, update_world_view).chain().after(InputSystem),
+        );
 }
 
 pub fn add_2d_axes(app: &mut App) {
@@ -11,7 +52,9 @@ pub fn add_2d_axes(app: &mut App) {
 }
 
 fn setup_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scale = 1.0 / INITIAL_PIXELS_PER_UNIT;
+    commands.spawn(camera);
 }
 
 fn setup_light(mut commands: Commands) {
@@ -21,19 +64,80 @@ fn setup_light(mut commands: Commands) {
     });
 }
 
-fn setup_axes(mut gizmos: Gizmos) {
-    let size = 300.0;
-    let zero = 0.0;
+fn pan_and_zoom(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    windows: Query<&Window>,
+    mut cameras: Query<(
+        &Camera,
+        &GlobalTransform,
+        &mut Transform,
+        &mut OrthographicProjection,
+    )>,
+) {
+    let drag: Vec2 = match mouse_buttons.pressed(MouseButton::Left) {
+        true => motion.read().map(|event| event.delta).sum(),
+        false => {
+            motion.clear();
+            Vec2::ZERO
+        }
+    };
+    let scroll: f32 = wheel.read().map(|event| event.y).sum();
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+
+    for (camera, global_transform, mut transform, mut projection) in cameras.iter_mut() {
+        // the screen's y axis points down
+        transform.translation.x -= drag.x * projection.scale;
+        transform.translation.y += drag.y * projection.scale;
+
+        if scroll != 0.0 {
+            let old_scale = projection.scale;
+            projection.scale = (old_scale * (1.0 - scroll * 0.1)).clamp(1e-5, 1e3);
+            // keep the point under the cursor in place
+            if let Some(cursor) =
+                cursor.and_then(|c| camera.viewport_to_world_2d(global_transform, c))
+            {
+                let center = transform.translation.truncate();
+                let new_center = cursor - (cursor - center) * (projection.scale / old_scale);
+                transform.translation.x = new_center.x;
+                transform.translation.y = new_center.y;
+            }
+        }
+    }
+}
+
+fn update_world_view(
+    mut view: ResMut<WorldView>,
+    cameras: Query<(&Camera, &Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    let Ok((camera, transform, projection)) = cameras.get_single() else {
+        return;
+    };
+    // the projection's area is only updated after `Update`, so with a new scale it would be a frame behind
+    let size = match camera.logical_viewport_size() {
+        Some(pixels) => pixels * projection.scale,
+        None => projection.area.size(),
+    };
+    *view = WorldView {
+        visible: Rect::from_center_size(transform.translation.truncate(), size),
+        units_per_pixel: projection.scale,
+    };
+}
+
+/// across the whole screen, wherever we pan to
+fn setup_axes(mut gizmos: Gizmos, view: Res<WorldView>) {
+    let visible = view.visible;
     // x
     gizmos.line_2d(
-        Vec2 { x: -size, y: zero },
-        Vec2 { x: size, y: zero },
+        Vec2::new(visible.min.x, 0.0),
+        Vec2::new(visible.max.x, 0.0),
         Color::GREEN,
     );
     // y
     gizmos.line_2d(
-        Vec2 { x: zero, y: -size },
-        Vec2 { x: zero, y: size },
+        Vec2::new(0.0, visible.min.y),
+        Vec2::new(0.0, visible.max.y),
         Color::RED,
     );
 }
@@ -0,0 +1,93 @@
+use crate::functions::to_world_3d;
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::prelude::*;
+
+/// the 3d counterpart of `add_2d_space`: a camera orbiting the origin (drag with the left mouse button to rotate,
+/// scroll to zoom), light and axes
+pub fn add_3d_space(app: &mut App) {
+    app.add_plugins(DefaultPlugins)
+        .add_systems(Startup, (setup_camera, setup_light))
+        .add_systems(Update, (orbit_camera, draw_axes));
+}
+
+/// position on a sphere around the origin, always looking at it
+#[derive(Component)]
+struct OrbitCamera {
+    radius: f32,
+    /// around the vertical axis, in radians
+    yaw: f32,
+    /// above the horizontal plane, in radians
+    pitch: f32,
+}
+
+impl OrbitCamera {
+    fn transform(&self) -> Transform {
+        let position = Vec3::new(
+            self.radius * self.pitch.cos() * self.yaw.sin(),
+            self.radius * self.pitch.sin(),
+            self.radius * self.pitch.cos() * self.yaw.cos(),
+        );
+        Transform::from_translation(position).looking_at(Vec3::ZERO, Vec3::Y)
+    }
+}
+
+fn setup_camera(mut commands: Commands) {
+    let orbit = OrbitCamera {
+        radius: 15.0,
+        yaw: 0.6,
+        pitch: 0.5,
+    };
+    commands.spawn((
+        Camera3dBundle {
+            transform: orbit.transform(),
+            ..default()
+        },
+        orbit,
+    ));
+}
+
+fn setup_light(mut commands: Commands) {
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 1.0,
+    });
+}
+
+fn orbit_camera(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut cameras: Query<(&mut OrbitCamera, &mut Transform)>,
+) {
+    let rotation: Vec2 = match mouse_buttons.pressed(MouseButton::Left) {
+        true => motion.read().map(|event| event.delta).sum(),
+        false => {
+            motion.clear();
+            Vec2::ZERO
+        }
+    };
+    let scroll: f32 = wheel.read().map(|event| event.y).sum();
+    if rotation == Vec2::ZERO && scroll == 0.0 {
+        return;
+    }
+
+    for (mut orbit, mut transform) in cameras.iter_mut() {
+        orbit.yaw -= rotation.x * 0.005;
+        // not quite straight up or down, where looking_at can't tell which way is up
+        orbit.pitch = (orbit.pitch + rotation.y * 0.005).clamp(-1.5, 1.5);
+        orbit.radius = (orbit.radius * (1.0 - scroll * 0.1)).clamp(2.0, 100.0);
+        *transform = orbit.transform();
+    }
+}
+
+fn draw_axes(mut gizmos: Gizmos) {
+    let size = 6.0;
+    // same colors as in 2d for x and y
+    for (axis, color) in [
+        (to_world_3d(size, 0.0, 0.0), Color::GREEN),
+        (to_world_3d(0.0, size, 0.0), Color::RED),
+        (to_world_3d(0.0, 0.0, size), Color::BLUE),
+    ] {
+        gizmos.line(-axis, axis, color);
+    }
+}